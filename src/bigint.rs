@@ -0,0 +1,323 @@
+use std::{
+    cmp::Ordering,
+    fmt::{self, Display},
+    ops::{Add, Div, Mul, Neg, Sub},
+};
+
+// sign and magnitude, the magnitude is little endian base 2^32 with no trailing zero limbs
+#[derive(Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct BigInt {
+    negative: bool,
+    limbs: Vec<u32>,
+}
+
+impl BigInt {
+    pub fn from_i64(v: i64) -> Self {
+        let mut magnitude = v.unsigned_abs();
+        let mut limbs = vec![];
+        while magnitude > 0 {
+            limbs.push(magnitude as u32);
+            magnitude >>= 32;
+        }
+        Self {
+            negative: v < 0,
+            limbs,
+        }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.limbs.is_empty()
+    }
+
+    fn normalized(mut self) -> Self {
+        while self.limbs.last() == Some(&0) {
+            self.limbs.pop();
+        }
+        if self.limbs.is_empty() {
+            self.negative = false;
+        }
+        self
+    }
+
+    fn from_magnitude(negative: bool, limbs: Vec<u32>) -> Self {
+        Self { negative, limbs }.normalized()
+    }
+
+    // truncating division and remainder, like the primitive integer types
+    pub fn div_rem(&self, rhs: &BigInt) -> (BigInt, BigInt) {
+        if rhs.is_zero() {
+            panic!("attempt to divide by zero");
+        }
+        let (quotient, remainder) = if rhs.limbs.len() == 1 {
+            let (q, r) = div_small(&self.limbs, rhs.limbs[0]);
+            (q, vec![r])
+        } else {
+            div_long(&self.limbs, &rhs.limbs)
+        };
+        (
+            Self::from_magnitude(self.negative != rhs.negative, quotient),
+            Self::from_magnitude(self.negative, remainder),
+        )
+    }
+
+    pub fn div_floor(&self, rhs: &BigInt) -> BigInt {
+        let (quotient, remainder) = self.div_rem(rhs);
+        if !remainder.is_zero() && remainder.negative != rhs.negative {
+            quotient - BigInt::from_i64(1)
+        } else {
+            quotient
+        }
+    }
+
+    // rem_euclid by a machine sized divisor, one pass over the limbs without allocating
+    pub fn rem_euclid_i64(&self, divisor: i64) -> i64 {
+        if divisor == 0 {
+            panic!("attempt to calculate the remainder with a divisor of zero");
+        }
+        let d = divisor.unsigned_abs() as u128;
+        let magnitude = self
+            .limbs
+            .iter()
+            .rev()
+            .fold(0, |r, &limb| ((r << 32) | limb as u128) % d);
+        if self.negative && magnitude != 0 {
+            (d - magnitude) as i64
+        } else {
+            magnitude as i64
+        }
+    }
+}
+
+fn cmp_magnitude(a: &[u32], b: &[u32]) -> Ordering {
+    a.len()
+        .cmp(&b.len())
+        .then_with(|| a.iter().rev().cmp(b.iter().rev()))
+}
+
+fn add_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut out = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0u64;
+    for i in 0..a.len().max(b.len()) {
+        let sum = *a.get(i).unwrap_or(&0) as u64 + *b.get(i).unwrap_or(&0) as u64 + carry;
+        out.push(sum as u32);
+        carry = sum >> 32;
+    }
+    out.push(carry as u32);
+    out
+}
+
+// requires a >= b
+fn sub_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut out = Vec::with_capacity(a.len());
+    let mut borrow = 0i64;
+    for (i, &limb) in a.iter().enumerate() {
+        let mut diff = limb as i64 - *b.get(i).unwrap_or(&0) as i64 - borrow;
+        borrow = (diff < 0) as i64;
+        if diff < 0 {
+            diff += 1 << 32;
+        }
+        out.push(diff as u32);
+    }
+    out
+}
+
+fn mul_magnitude(a: &[u32], b: &[u32]) -> Vec<u32> {
+    let mut out = vec![0u32; a.len() + b.len()];
+    for (i, &x) in a.iter().enumerate() {
+        let mut carry = 0u64;
+        for (j, &y) in b.iter().enumerate() {
+            let product = x as u64 * y as u64 + out[i + j] as u64 + carry;
+            out[i + j] = product as u32;
+            carry = product >> 32;
+        }
+        out[i + b.len()] = carry as u32;
+    }
+    out
+}
+
+fn div_small(a: &[u32], d: u32) -> (Vec<u32>, u32) {
+    let mut quotient = vec![0u32; a.len()];
+    let mut remainder = 0u64;
+    for i in (0..a.len()).rev() {
+        let current = (remainder << 32) | a[i] as u64;
+        quotient[i] = (current / d as u64) as u32;
+        remainder = current % d as u64;
+    }
+    (quotient, remainder as u32)
+}
+
+// binary long division, only used for divisors wider than one limb
+fn div_long(a: &[u32], b: &[u32]) -> (Vec<u32>, Vec<u32>) {
+    let mut quotient = vec![0u32; a.len()];
+    let mut remainder: Vec<u32> = vec![];
+    for bit in (0..a.len() * 32).rev() {
+        remainder = add_magnitude(&remainder, &remainder);
+        remainder[0] |= (a[bit / 32] >> (bit % 32)) & 1;
+        while remainder.last() == Some(&0) {
+            remainder.pop();
+        }
+        if cmp_magnitude(&remainder, b) != Ordering::Less {
+            remainder = sub_magnitude(&remainder, b);
+            while remainder.last() == Some(&0) {
+                remainder.pop();
+            }
+            quotient[bit / 32] |= 1 << (bit % 32);
+        }
+    }
+    (quotient, remainder)
+}
+
+impl Add for BigInt {
+    type Output = BigInt;
+
+    fn add(self, rhs: BigInt) -> BigInt {
+        if self.negative == rhs.negative {
+            return Self::from_magnitude(self.negative, add_magnitude(&self.limbs, &rhs.limbs));
+        }
+        match cmp_magnitude(&self.limbs, &rhs.limbs) {
+            Ordering::Less => {
+                Self::from_magnitude(rhs.negative, sub_magnitude(&rhs.limbs, &self.limbs))
+            }
+            _ => Self::from_magnitude(self.negative, sub_magnitude(&self.limbs, &rhs.limbs)),
+        }
+    }
+}
+
+impl Neg for BigInt {
+    type Output = BigInt;
+
+    fn neg(self) -> BigInt {
+        Self::from_magnitude(!self.negative, self.limbs)
+    }
+}
+
+impl Sub for BigInt {
+    type Output = BigInt;
+
+    fn sub(self, rhs: BigInt) -> BigInt {
+        self + -rhs
+    }
+}
+
+impl Mul for BigInt {
+    type Output = BigInt;
+
+    fn mul(self, rhs: BigInt) -> BigInt {
+        Self::from_magnitude(
+            self.negative != rhs.negative,
+            mul_magnitude(&self.limbs, &rhs.limbs),
+        )
+    }
+}
+
+impl Div for BigInt {
+    type Output = BigInt;
+
+    fn div(self, rhs: BigInt) -> BigInt {
+        self.div_rem(&rhs).0
+    }
+}
+
+impl Ord for BigInt {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self.negative, other.negative) {
+            (false, true) => Ordering::Greater,
+            (true, false) => Ordering::Less,
+            (false, false) => cmp_magnitude(&self.limbs, &other.limbs),
+            (true, true) => cmp_magnitude(&other.limbs, &self.limbs),
+        }
+    }
+}
+
+impl PartialOrd for BigInt {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for BigInt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_zero() {
+            return write!(f, "0");
+        }
+        let mut digits = vec![];
+        let mut magnitude = self.limbs.clone();
+        while !magnitude.is_empty() {
+            let (quotient, remainder) = div_small(&magnitude, 1_000_000_000);
+            digits.push(remainder);
+            magnitude = quotient;
+            while magnitude.last() == Some(&0) {
+                magnitude.pop();
+            }
+        }
+        if self.negative {
+            write!(f, "-")?;
+        }
+        write!(f, "{}", digits.pop().unwrap())?;
+        for chunk in digits.iter().rev() {
+            write!(f, "{:09}", chunk)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::BigInt;
+
+    fn big(v: i64) -> BigInt {
+        BigInt::from_i64(v)
+    }
+
+    #[test]
+    fn arithmetic_matches_i128() {
+        let values = [
+            0,
+            1,
+            -1,
+            7,
+            -13,
+            4_294_967_296,
+            -9_876_543_210_123,
+            i64::MAX,
+            i64::MIN + 1,
+        ];
+        for &a in &values {
+            for &b in &values {
+                let (x, y) = (a as i128, b as i128);
+                assert_eq!((x + y).to_string(), (big(a) + big(b)).to_string());
+                assert_eq!((x - y).to_string(), (big(a) - big(b)).to_string());
+                assert_eq!((x * y).to_string(), (big(a) * big(b)).to_string());
+                assert_eq!(x.cmp(&y), big(a).cmp(&big(b)));
+                if b != 0 {
+                    assert_eq!((x / y).to_string(), (big(a) / big(b)).to_string());
+                    let floor = x / y - (x % y != 0 && (x % y < 0) != (y < 0)) as i128;
+                    assert_eq!(floor.to_string(), big(a).div_floor(&big(b)).to_string());
+                    assert_eq!(x.rem_euclid(y) as i64, big(a).rem_euclid_i64(b));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn large_products() {
+        let mut v = big(1);
+        for _ in 0..30 {
+            v = v * big(1_000_000_007);
+        }
+        assert_eq!(0, v.rem_euclid_i64(1_000_000_007));
+        assert_eq!(1, (v.clone() + big(1)).rem_euclid_i64(1_000_000_007));
+        assert_eq!(
+            1_000_000_006,
+            (-v.clone() - big(1)).rem_euclid_i64(-1_000_000_007)
+        );
+        let below = v.clone() - big(1);
+        let expected = below.clone() - below.div_floor(&big(i64::MAX)) * big(i64::MAX);
+        assert_eq!(
+            expected.to_string(),
+            below.rem_euclid_i64(i64::MAX).to_string()
+        );
+        let half = v.clone() / (big(1_000_000_007) * big(1_000_000_007));
+        assert_eq!(v, half * big(1_000_000_007) * big(1_000_000_007));
+    }
+}
@@ -0,0 +1,51 @@
+use std::{fs, str::FromStr};
+
+use crate::input::get_input;
+
+pub struct Args {
+    flags: Vec<(String, Option<String>)>,
+}
+
+impl Args {
+    pub fn parse(args: &[String]) -> Self {
+        let mut flags: Vec<(String, Option<String>)> = vec![];
+        for arg in args {
+            match arg.strip_prefix("--") {
+                Some(name) => flags.push((name.to_string(), None)),
+                None => match flags.last_mut() {
+                    Some((_, value @ None)) => *value = Some(arg.clone()),
+                    _ => panic!("unexpected argument {}", arg),
+                },
+            }
+        }
+        Self { flags }
+    }
+
+    pub fn flag(&self, name: &str) -> bool {
+        self.flags.iter().any(|(n, _)| n == name)
+    }
+
+    pub fn value<T: FromStr>(&self, name: &str) -> Option<T> {
+        self.flags
+            .iter()
+            .find(|(n, _)| n == name)
+            .and_then(|(_, v)| v.as_ref())
+            .map(|v| {
+                v.parse()
+                    .unwrap_or_else(|_| panic!("invalid value {} for --{}", v, name))
+            })
+    }
+
+    pub fn input(&self, day: i32) -> String {
+        match self.value::<String>("input") {
+            Some(path) => {
+                fs::read_to_string(&path).unwrap_or_else(|e| panic!("cannot read {}: {}", path, e))
+            }
+            None => get_input(
+                day,
+                self.flag("example"),
+                self.value::<String>("qualifier").as_deref(),
+            ),
+        }
+    }
+}
@@ -0,0 +1,268 @@
+use std::fmt::{self, Display};
+
+use crate::problem::Problem;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Registers {
+    pub x: i32,
+    pub pc: usize,
+    pub cycle: i32,
+}
+
+pub struct OpSpec {
+    pub mnemonic: &'static str,
+    pub cycles: usize,
+    pub arity: usize,
+    pub execute: fn(&mut Registers, &[i32]),
+}
+
+// new instructions only need an entry here, the cpu looks everything else up by opcode
+pub static INSTRUCTION_SET: &[OpSpec] = &[
+    OpSpec {
+        mnemonic: "noop",
+        cycles: 1,
+        arity: 0,
+        execute: |_, _| {},
+    },
+    OpSpec {
+        mnemonic: "addx",
+        cycles: 2,
+        arity: 1,
+        execute: |registers, args| registers.x += args[0],
+    },
+    // a target outside the program halts it
+    OpSpec {
+        mnemonic: "jmp",
+        cycles: 1,
+        arity: 1,
+        execute: |registers, args| registers.pc = usize::try_from(args[0]).unwrap_or(usize::MAX),
+    },
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Instruction {
+    pub opcode: usize,
+    pub args: Vec<i32>,
+}
+
+impl Instruction {
+    pub fn spec(&self) -> &'static OpSpec {
+        &INSTRUCTION_SET[self.opcode]
+    }
+}
+
+impl Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.spec().mnemonic)?;
+        for arg in &self.args {
+            write!(f, " {}", arg)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Program {
+    pub instructions: Vec<Instruction>,
+    // label name and the index of the instruction it points at
+    pub labels: Vec<(String, usize)>,
+}
+
+impl Program {
+    // one instruction per line, `name:` defines a label and `;` starts a comment, an operand
+    // that is not a number is the index of the label's instruction
+    pub fn assemble(source: &str) -> Result<Self, Problem> {
+        let mut program = Program::default();
+        // labels can be used before they are defined, so they are resolved at the end
+        let mut references = vec![];
+        for (i, line) in source.lines().enumerate() {
+            let error = |message: String| Problem::new(i + 1, message);
+            let mut line = line.split(';').next().unwrap().trim();
+            if let Some((label, rest)) = line.split_once(':') {
+                let label = label.trim();
+                if label.is_empty() || label.contains(char::is_whitespace) {
+                    return Err(error(format!("invalid label {:?}", label)));
+                }
+                if program.label(label).is_some() {
+                    return Err(error(format!("duplicate label {}", label)));
+                }
+                program
+                    .labels
+                    .push((label.to_string(), program.instructions.len()));
+                line = rest.trim();
+            }
+            if line.is_empty() {
+                continue;
+            }
+            let mut elems = line.split_whitespace();
+            let mnemonic = elems.next().unwrap();
+            let opcode = INSTRUCTION_SET
+                .iter()
+                .position(|spec| spec.mnemonic == mnemonic)
+                .ok_or_else(|| error(format!("unknown instruction {}", mnemonic)))?;
+            let mut args = vec![];
+            for arg in elems {
+                match arg.parse() {
+                    Ok(value) => args.push(value),
+                    Err(_) if arg.starts_with(|c: char| c.is_alphabetic() || c == '_') => {
+                        references.push((i + 1, program.instructions.len(), args.len(), arg));
+                        args.push(0);
+                    }
+                    Err(_) => return Err(error(format!("invalid operand {}", arg))),
+                }
+            }
+            if args.len() != INSTRUCTION_SET[opcode].arity {
+                return Err(error(format!(
+                    "{} takes {} operand(s), got {}",
+                    mnemonic,
+                    INSTRUCTION_SET[opcode].arity,
+                    args.len()
+                )));
+            }
+            program.instructions.push(Instruction { opcode, args });
+        }
+        for (line, instruction, arg, label) in references {
+            let index = program
+                .label(label)
+                .ok_or_else(|| Problem::new(line, format!("unknown label {}", label)))?;
+            program.instructions[instruction].args[arg] = index as i32;
+        }
+        Ok(program)
+    }
+
+    pub fn disassemble(&self) -> String {
+        let mut out = String::new();
+        for index in 0..=self.instructions.len() {
+            for (label, _) in self.labels.iter().filter(|(_, i)| *i == index) {
+                out.push_str(&format!("{}:\n", label));
+            }
+            if let Some(instruction) = self.instructions.get(index) {
+                out.push_str(&format!("{}\n", instruction));
+            }
+        }
+        out
+    }
+
+    pub fn label(&self, name: &str) -> Option<usize> {
+        self.labels
+            .iter()
+            .find(|(label, _)| label == name)
+            .map(|(_, index)| *index)
+    }
+}
+
+pub struct Cpu {
+    pub registers: Registers,
+    pub program: Program,
+    // cycles left before the instruction at `pc` completes, 0 when none is in flight
+    remaining: usize,
+}
+
+impl Cpu {
+    pub fn new(program: Program) -> Self {
+        Self {
+            registers: Registers {
+                x: 1,
+                pc: 0,
+                cycle: 1,
+            },
+            program,
+            remaining: 0,
+        }
+    }
+
+    pub fn halted(&self) -> bool {
+        self.remaining == 0 && self.registers.pc >= self.program.instructions.len()
+    }
+
+    // runs a single clock cycle, the registers afterwards hold the values seen during the next one
+    pub fn step(&mut self) -> bool {
+        if self.halted() {
+            return false;
+        }
+        let instruction = &self.program.instructions[self.registers.pc];
+        if self.remaining == 0 {
+            self.remaining = instruction.spec().cycles;
+        }
+        self.remaining -= 1;
+        if self.remaining == 0 {
+            // before executing, so jumps can overwrite it
+            self.registers.pc += 1;
+            (instruction.spec().execute)(&mut self.registers, &instruction.args);
+        }
+        self.registers.cycle += 1;
+        true
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Cpu, Problem, Program};
+
+    #[test]
+    fn assemble_labels_and_comments() {
+        let program =
+            Program::assemble("; setup\nstart:\n  addx 3 ; bump\nnoop\nloop: addx -5\nend:\n")
+                .unwrap();
+        assert_eq!(3, program.instructions.len());
+        assert_eq!(Some(0), program.label("start"));
+        assert_eq!(Some(2), program.label("loop"));
+        assert_eq!(Some(3), program.label("end"));
+    }
+
+    #[test]
+    fn disassemble_round_trip() {
+        let program = Program::assemble("a:\nnoop\naddx 3\nb:\nc:\naddx -5\nd:\n").unwrap();
+        let source = program.disassemble();
+        assert_eq!("a:\nnoop\naddx 3\nb:\nc:\naddx -5\nd:\n", source);
+        assert_eq!(program, Program::assemble(&source).unwrap());
+    }
+
+    #[test]
+    fn assemble_errors() {
+        assert_eq!(
+            Err(Problem::new(2, "unknown instruction mulx")),
+            Program::assemble("noop\nmulx 2")
+        );
+        assert_eq!(
+            Err(Problem::new(1, "addx takes 1 operand(s), got 0")),
+            Program::assemble("addx")
+        );
+    }
+
+    #[test]
+    fn jumps_to_labels() {
+        let program =
+            Program::assemble("jmp skip\nloop: addx 5\nskip: addx 1\njmp loop\n").unwrap();
+        assert_eq!(
+            "jmp 2\nloop:\naddx 5\nskip:\naddx 1\njmp 1\n",
+            program.disassemble()
+        );
+        let mut cpu = Cpu::new(program);
+        let mut xs = vec![];
+        for _ in 0..10 {
+            cpu.step();
+            xs.push(cpu.registers.x);
+        }
+        assert_eq!(vec![1, 1, 2, 2, 2, 7, 7, 8, 8, 8], xs);
+        assert_eq!(
+            Err(Problem::new(2, "unknown label nowhere")),
+            Program::assemble("noop\njmp nowhere\n")
+        );
+        let mut cpu = Cpu::new(Program::assemble("jmp -1\naddx 3").unwrap());
+        assert!(cpu.step());
+        assert!(cpu.halted());
+    }
+
+    #[test]
+    fn step_cycles() {
+        let mut cpu = Cpu::new(Program::assemble("noop\naddx 3\naddx -5").unwrap());
+        let mut xs = vec![cpu.registers.x];
+        while cpu.step() {
+            xs.push(cpu.registers.x);
+        }
+        assert_eq!(vec![1, 1, 1, 4, 4, -1], xs);
+        assert_eq!(6, cpu.registers.cycle);
+        assert!(cpu.halted());
+    }
+}
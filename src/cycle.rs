@@ -0,0 +1,104 @@
+// Brent's cycle detection over the states reached by repeatedly applying `step` to `start`.
+// Returns (mu, lambda): the state after mu steps is the first one on the cycle, and the
+// cycle repeats every lambda steps. Gives up with None rather than call `step` more than
+// `limit` times.
+pub fn find_cycle<S: Clone + PartialEq>(
+    start: S,
+    limit: usize,
+    mut step: impl FnMut(&S) -> S,
+) -> Option<(usize, usize)> {
+    let mut calls = 0;
+    let mut step = |state: &S| {
+        calls += 1;
+        (calls <= limit).then(|| step(state))
+    };
+    let mut power = 1;
+    let mut lambda = 1;
+    let mut tortoise = start.clone();
+    let mut hare = step(&start)?;
+    while tortoise != hare {
+        if power == lambda {
+            tortoise = hare.clone();
+            power *= 2;
+            lambda = 0;
+        }
+        hare = step(&hare)?;
+        lambda += 1;
+    }
+
+    let mut mu = 0;
+    let mut tortoise = start.clone();
+    let mut hare = start;
+    for _ in 0..lambda {
+        hare = step(&hare)?;
+    }
+    while tortoise != hare {
+        tortoise = step(&tortoise)?;
+        hare = step(&hare)?;
+        mu += 1;
+    }
+    Some((mu, lambda))
+}
+
+// splits `steps` into the lead-in before the cycle, the number of whole cycles and the leftover steps
+pub fn split_steps(steps: u64, mu: usize, lambda: usize) -> (u64, u64, u64) {
+    let lead_in = steps.min(mu as u64);
+    let rest = steps - lead_in;
+    (lead_in, rest / lambda as u64, rest % lambda as u64)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{find_cycle, split_steps};
+
+    #[test]
+    fn rho_shaped_sequence() {
+        // 0 -> 1 -> 2 -> 3 -> 4 -> 5 -> 2
+        let next = |s: &u32| if *s == 5 { 2 } else { s + 1 };
+        assert_eq!(Some((2, 4)), find_cycle(0, usize::MAX, next));
+        assert_eq!(Some((0, 4)), find_cycle(2, usize::MAX, next));
+    }
+
+    #[test]
+    fn limit() {
+        let mut calls = 0;
+        let next = |s: &u32| {
+            calls += 1;
+            s + 1
+        };
+        assert_eq!(None, find_cycle(0, 100, next));
+        assert_eq!(100, calls);
+        let rho = |s: &u32| if *s == 5 { 2 } else { s + 1 };
+        assert_eq!(None, find_cycle(0, 5, rho));
+    }
+
+    #[test]
+    fn fixed_point() {
+        assert_eq!(
+            Some((3, 1)),
+            find_cycle(3u32, usize::MAX, |s| s.saturating_sub(1))
+        );
+    }
+
+    #[test]
+    fn modular_sequence() {
+        // x -> x^2 + 1 mod 255 starting at 3
+        let mut seen = vec![3u32];
+        while !seen[..seen.len() - 1].contains(seen.last().unwrap()) {
+            let last = *seen.last().unwrap();
+            seen.push((last * last + 1) % 255);
+        }
+        let first = seen.iter().position(|s| s == seen.last().unwrap()).unwrap();
+        let expected = (first, seen.len() - 1 - first);
+        assert_eq!(
+            Some(expected),
+            find_cycle(3u32, usize::MAX, |s| (s * s + 1) % 255)
+        );
+    }
+
+    #[test]
+    fn split() {
+        assert_eq!((2, 3, 1), split_steps(15, 2, 4));
+        assert_eq!((1, 0, 0), split_steps(1, 2, 4));
+    }
+}
@@ -1,4 +1,12 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, BufRead, Write},
+};
+
 use crate::{
+    cli::Args,
+    cpu::{Cpu, Program, Registers},
     input::get_input,
     solution::{Solution, SolutionPair},
 };
@@ -11,135 +19,376 @@ pub fn solve() -> SolutionPair {
 }
 
 fn solve1(input: &str) -> Solution {
-    let (mut crt, commands) = parse_input(input);
-    let signal_strength = crt.iterate_commands(&commands);
+    let mut crt = parse_input(input);
+    let signal_strength = crt.iterate_commands();
     Solution::I32(signal_strength)
 }
 
 fn solve2(input: &str) -> Solution {
-    let (mut crt, commands) = parse_input(input);
-    let pixels = crt.iterate_commands_pixels(&commands);
-    let screen = pixels
-        .split("")
-        .enumerate()
-        .map(|(i, e)| {
-            if i != 1 && i % 40 == 1 {
-                format!("\n{}", e)
-            } else {
-                e.to_string()
-            }
-        })
-        .collect::<Vec<_>>()
-        .join("");
-    Solution::String(screen)
+    let mut crt = parse_input(input);
+    crt.iterate_commands_pixels();
+    Solution::String(crt.text())
+}
+
+pub fn tool(args: &Args) {
+    let input = args.input(DAY);
+    let width = args.value("width").unwrap_or(40);
+    let height = args.value("height").unwrap_or(6);
+    let sprite_width = args.value("sprite").unwrap_or(3);
+    if width == 0 || height == 0 {
+        println!("a screen needs at least one row and one column");
+        return;
+    }
+    if sprite_width <= 0 {
+        println!("a sprite needs to be at least one pixel wide");
+        return;
+    }
+    if args.flag("disassemble") {
+        print!("{}", assemble(&input).disassemble());
+    }
+    let crt = CRT::new(Cpu::new(assemble(&input)), width, height, sprite_width);
+    if args.flag("debug") {
+        let mut debugger = Debugger::new(crt);
+        debugger.run(io::stdin().lock(), &mut io::stdout()).unwrap();
+    } else if let Some(format) = args.value::<String>("export") {
+        let mut crt = crt;
+        crt.iterate_commands_pixels();
+        let image = match format.as_str() {
+            "pbm" => crt.pbm(),
+            "ansi" => crt.ansi().into_bytes(),
+            "text" => format!("{}\n", crt.text()).into_bytes(),
+            _ => panic!("unknown export format {}", format),
+        };
+        match args.value::<String>("output") {
+            Some(path) => fs::write(path, image).unwrap(),
+            None => io::stdout().write_all(&image).unwrap(),
+        }
+    }
 }
 
 struct CRT {
-    cycle: i32,
-    current_command: Option<Command>,
-    x: i32,
+    cpu: Cpu,
+    width: usize,
+    height: usize,
+    sprite_width: i32,
+    pixels: Vec<bool>,
+    // pixels drawn so far, capped at one full screen
+    drawn: usize,
 }
 
 impl CRT {
-    fn set_command(&mut self, cmd: Command) -> () {
-        self.current_command = Some(cmd);
-    }
-
-    fn iterate(&mut self) -> () {
-        self.cycle += 1;
-        match self.current_command {
-            Some(Command::AddX(cycles_remaining, value)) => {
-                if cycles_remaining > 1 {
-                    self.current_command = Some(Command::AddX(cycles_remaining - 1, value));
-                } else {
-                    self.current_command = None;
-                    self.x += value;
-                }
-            }
-            Some(Command::Noop(cycles_remaining)) => {
-                if cycles_remaining > 1 {
-                    self.current_command = Some(Command::Noop(cycles_remaining - 1));
-                } else {
-                    self.current_command = None;
-                }
-            }
-            None => panic!("Cannot iterate without a current command"),
+    fn new(cpu: Cpu, width: usize, height: usize, sprite_width: i32) -> Self {
+        Self {
+            cpu,
+            width,
+            height,
+            sprite_width,
+            pixels: vec![false; width * height],
+            drawn: 0,
         }
     }
 
-    fn iterate_commands(&mut self, commands: &[Command]) -> i32 {
+    fn iterate_commands(&mut self) -> i32 {
         let mut signal_strength = 0;
-        for command in commands {
-            self.set_command(*command);
-            while let Some(_) = self.current_command {
-                self.iterate();
+        while self.tick() {
+            signal_strength += self.signal_strength().unwrap_or(0);
+        }
+        signal_strength
+    }
 
-                if (self.cycle - 20) % 40 == 0 {
-                    signal_strength += self.cycle * self.x;
-                }
+    fn iterate_commands_pixels(&mut self) -> &[bool] {
+        while self.tick() {}
+        &self.pixels
+    }
+
+    // draws the pixel for the current cycle and then runs it
+    fn tick(&mut self) -> bool {
+        if self.cpu.halted() {
+            return false;
+        }
+        let position = (self.cpu.registers.cycle - 1) as usize % self.pixels.len();
+        self.pixels[position] = self.lit();
+        self.drawn = self.pixels.len().min(self.drawn + 1);
+        self.cpu.step()
+    }
+
+    fn signal_strength(&self) -> Option<i32> {
+        let Registers { cycle, x, .. } = self.cpu.registers;
+        ((cycle - 20) % 40 == 0).then_some(cycle * x)
+    }
+
+    fn lit(&self) -> bool {
+        let Registers { cycle, x, .. } = self.cpu.registers;
+        let column = (cycle - 1) % self.width as i32;
+        (x - (self.sprite_width - 1) / 2..=x + self.sprite_width / 2).contains(&column)
+    }
+
+    fn rows(&self) -> impl Iterator<Item = &[bool]> {
+        self.pixels[..self.drawn].chunks(self.width)
+    }
+
+    fn text(&self) -> String {
+        self.rows()
+            .map(|row| row.iter().map(|&p| if p { '#' } else { '.' }).collect())
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    // two screen rows per line of half block characters
+    fn ansi(&self) -> String {
+        let mut out = String::new();
+        for y in (0..self.height).step_by(2) {
+            for x in 0..self.width {
+                let top = self.pixels[y * self.width + x];
+                let bottom = y + 1 < self.height && self.pixels[(y + 1) * self.width + x];
+                out.push(match (top, bottom) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                });
             }
+            out.push('\n');
         }
-        signal_strength
+        out
+    }
+
+    // binary portable bitmap, lit pixels are black
+    fn pbm(&self) -> Vec<u8> {
+        let mut out = format!("P4\n{} {}\n", self.width, self.height).into_bytes();
+        for row in self.pixels.chunks(self.width) {
+            for byte in row.chunks(8) {
+                out.push(
+                    byte.iter()
+                        .enumerate()
+                        .fold(0, |acc, (i, &p)| acc | ((p as u8) << (7 - i))),
+                );
+            }
+        }
+        out
     }
+}
+
+fn assemble(input: &str) -> Program {
+    Program::assemble(input).unwrap_or_else(|e| panic!("{}", e))
+}
+
+fn parse_input(input: &str) -> CRT {
+    CRT::new(Cpu::new(assemble(input)), 40, 6, 3)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Register {
+    X,
+    Pc,
+    Cycle,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Comparison {
+    Lt,
+    Le,
+    Eq,
+    Ne,
+    Ge,
+    Gt,
+}
 
-    fn iterate_commands_pixels(&mut self, commands: &[Command]) -> String {
-        let mut pixels = "".into();
-        for command in commands {
-            self.set_command(*command);
-            while let Some(_) = self.current_command {
-                pixels = format!("{}{}", pixels, self.pixel());
-                self.iterate();
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Breakpoint {
+    Cycle(i32),
+    Instruction(usize),
+    Condition(Register, Comparison, i32),
+}
+
+impl Breakpoint {
+    fn parse(args: &[&str], program: &Program) -> Result<Self, String> {
+        match args {
+            ["cycle", n] => n
+                .parse()
+                .map(Breakpoint::Cycle)
+                .map_err(|_| format!("invalid cycle {}", n)),
+            ["pc", target] => target
+                .parse()
+                .ok()
+                .or_else(|| program.label(target))
+                .map(Breakpoint::Instruction)
+                .ok_or_else(|| format!("invalid instruction index or label {}", target)),
+            [register, comparison, value] => {
+                let register = match *register {
+                    "x" => Register::X,
+                    "pc" => Register::Pc,
+                    "cycle" => Register::Cycle,
+                    _ => return Err(format!("unknown register {}", register)),
+                };
+                let comparison = match *comparison {
+                    "<" => Comparison::Lt,
+                    "<=" => Comparison::Le,
+                    "==" => Comparison::Eq,
+                    "!=" => Comparison::Ne,
+                    ">=" => Comparison::Ge,
+                    ">" => Comparison::Gt,
+                    _ => return Err(format!("unknown comparison {}", comparison)),
+                };
+                value
+                    .parse()
+                    .map(|value| Breakpoint::Condition(register, comparison, value))
+                    .map_err(|_| format!("invalid value {}", value))
+            }
+            _ => {
+                Err("usage: break cycle <n> | break pc <index|label> | break <reg> <op> <n>".into())
             }
         }
-        pixels
     }
 
-    fn pixel(&self) -> char {
-        if ((self.x - 1) % 40) == ((self.cycle - 1) % 40)
-            || ((self.x) % 40) == ((self.cycle - 1) % 40)
-            || ((self.x + 1) % 40) == ((self.cycle - 1) % 40)
-        {
-            '#'
-        } else {
-            '.'
+    fn holds(&self, registers: &Registers) -> bool {
+        match *self {
+            Breakpoint::Cycle(cycle) => registers.cycle == cycle,
+            Breakpoint::Instruction(pc) => registers.pc == pc,
+            Breakpoint::Condition(register, comparison, value) => {
+                let actual = match register {
+                    Register::X => registers.x,
+                    Register::Pc => registers.pc as i32,
+                    Register::Cycle => registers.cycle,
+                };
+                match comparison {
+                    Comparison::Lt => actual < value,
+                    Comparison::Le => actual <= value,
+                    Comparison::Eq => actual == value,
+                    Comparison::Ne => actual != value,
+                    Comparison::Ge => actual >= value,
+                    Comparison::Gt => actual > value,
+                }
+            }
         }
     }
 }
 
-#[derive(Copy, Clone)]
-enum Command {
-    AddX(usize, i32),
-    Noop(usize),
+struct Debugger {
+    crt: CRT,
+    // ids keep their numbers when others are deleted and are never reused
+    breakpoints: BTreeMap<usize, Breakpoint>,
+    next_breakpoint: usize,
+    watch_signal: bool,
+    signal_strength: i32,
 }
 
-fn parse_input(input: &str) -> (CRT, Vec<Command>) {
-    let commands = input
-        .split("\n")
-        .filter(|line| !line.trim().is_empty())
-        .map(|line| {
-            let elems: Vec<_> = line.split(" ").collect();
-            match elems.first() {
-                Some(&"addx") => Command::AddX(2, elems[1].parse().unwrap()),
-                Some(&"noop") => Command::Noop(1),
-                other => panic!("Unknown command {:#?}", other),
+impl Debugger {
+    fn new(crt: CRT) -> Self {
+        Self {
+            crt,
+            breakpoints: BTreeMap::new(),
+            next_breakpoint: 1,
+            watch_signal: false,
+            signal_strength: 0,
+        }
+    }
+
+    fn run(&mut self, input: impl BufRead, out: &mut impl Write) -> io::Result<()> {
+        self.registers(out)?;
+        for line in input.lines() {
+            let line = line?;
+            let words: Vec<_> = line.split_whitespace().collect();
+            match words.as_slice() {
+                [] => continue,
+                ["break" | "b", args @ ..] => {
+                    match Breakpoint::parse(args, &self.crt.cpu.program) {
+                        Ok(breakpoint) => {
+                            let id = self.next_breakpoint;
+                            self.next_breakpoint += 1;
+                            self.breakpoints.insert(id, breakpoint);
+                            writeln!(out, "breakpoint {}: {:?}", id, breakpoint)?;
+                        }
+                        Err(e) => writeln!(out, "{}", e)?,
+                    }
+                }
+                ["delete" | "d", n] => {
+                    let deleted = n.parse().ok().and_then(|n| self.breakpoints.remove(&n));
+                    if deleted.is_none() {
+                        writeln!(out, "no breakpoint {}", n)?;
+                    }
+                }
+                ["breakpoints"] => {
+                    for (id, breakpoint) in &self.breakpoints {
+                        writeln!(out, "breakpoint {}: {:?}", id, breakpoint)?;
+                    }
+                }
+                ["watch", "signal"] => self.watch_signal = true,
+                ["unwatch", "signal"] => self.watch_signal = false,
+                ["step" | "s", count @ ..] => {
+                    let count = count.first().and_then(|c| c.parse().ok()).unwrap_or(1);
+                    for _ in 0..count {
+                        if !self.tick(out)? || self.stop_at_breakpoint(out)? {
+                            break;
+                        }
+                    }
+                    self.registers(out)?;
+                }
+                ["continue" | "c"] => {
+                    while self.tick(out)? && !self.stop_at_breakpoint(out)? {}
+                    self.registers(out)?;
+                }
+                ["registers" | "regs" | "r"] => self.registers(out)?,
+                ["screen"] => self.screen(out)?,
+                ["quit" | "q"] => break,
+                _ => writeln!(out, "unknown command {}", line)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn tick(&mut self, out: &mut impl Write) -> io::Result<bool> {
+        if !self.crt.tick() {
+            writeln!(out, "program halted")?;
+            return Ok(false);
+        }
+        if let Some(strength) = self.crt.signal_strength() {
+            self.signal_strength += strength;
+            if self.watch_signal {
+                let Registers { cycle, x, .. } = self.crt.cpu.registers;
+                writeln!(
+                    out,
+                    "cycle {}: x = {}, signal strength {} (total {})",
+                    cycle, x, strength, self.signal_strength
+                )?;
             }
-        })
-        .collect();
-    (
-        CRT {
-            cycle: 1,
-            current_command: None,
-            x: 1,
-        },
-        commands,
-    )
+        }
+        Ok(true)
+    }
+
+    // reports the lowest numbered breakpoint that holds now
+    fn stop_at_breakpoint(&self, out: &mut impl Write) -> io::Result<bool> {
+        let hit = self
+            .breakpoints
+            .iter()
+            .find(|(_, b)| b.holds(&self.crt.cpu.registers));
+        if let Some((id, _)) = hit {
+            writeln!(out, "hit breakpoint {}", id)?;
+        }
+        Ok(hit.is_some())
+    }
+
+    fn registers(&self, out: &mut impl Write) -> io::Result<()> {
+        let Registers { x, pc, cycle } = self.crt.cpu.registers;
+        write!(out, "cycle {} pc {} x {}", cycle, pc, x)?;
+        match self.crt.cpu.program.instructions.get(pc) {
+            Some(instruction) => writeln!(out, " | {}", instruction),
+            None => writeln!(out, " | halted"),
+        }
+    }
+
+    fn screen(&self, out: &mut impl Write) -> io::Result<()> {
+        writeln!(out, "{}", self.crt.text())
+    }
 }
 
 #[cfg(test)]
 mod test {
     use crate::{input::get_input, solution::Solution};
 
-    use super::{solve1, solve2, DAY};
+    use crate::cpu::{Cpu, Program};
+
+    use super::{parse_input, solve1, solve2, Debugger, CRT, DAY};
 
     fn sample_input() -> String {
         get_input(DAY, true, None)
@@ -165,4 +414,64 @@ mod test {
             solve2(&sample_input())
         );
     }
+
+    fn debug(commands: &str) -> String {
+        let mut debugger = Debugger::new(parse_input(&sample_input()));
+        let mut out = vec![];
+        debugger.run(commands.as_bytes(), &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn debugger_breakpoints() {
+        let out = debug(
+            "break cycle 20\ncontinue\nbreak x > 20\nc\nb pc 5\ndelete 1\nc\ndelete 0\ndelete 2\nd 2\nbreakpoints\nstep 3\nb cycle 30\nstep 5\n",
+        );
+        assert_eq!(
+            "cycle 1 pc 0 x 1 | addx 15
+breakpoint 1: Cycle(20)
+hit breakpoint 1
+cycle 20 pc 10 x 21 | addx -1
+breakpoint 2: Condition(X, Gt, 20)
+hit breakpoint 2
+cycle 21 pc 10 x 21 | addx -1
+breakpoint 3: Instruction(5)
+hit breakpoint 2
+cycle 24 pc 12 x 25 | addx -1
+no breakpoint 0
+no breakpoint 2
+breakpoint 3: Instruction(5)
+cycle 27 pc 13 x 24 | addx 5
+breakpoint 4: Cycle(30)
+hit breakpoint 4
+cycle 30 pc 15 x 28 | addx 5
+",
+            out
+        );
+    }
+
+    #[test]
+    fn debugger_watch_signal() {
+        let out = debug("watch signal\nstep 60\nscreen\n");
+        assert_eq!(
+            "cycle 1 pc 0 x 1 | addx 15
+cycle 20: x = 21, signal strength 420 (total 420)
+cycle 60: x = 19, signal strength 1140 (total 1560)
+cycle 61 pc 32 x 19 | addx -3
+##..##..##..##..##..##..##..##..##..##..
+###...###...###...##
+",
+            out
+        );
+    }
+
+    #[test]
+    fn geometry() {
+        let program = Program::assemble("noop\naddx 5\nnoop\nnoop\naddx -3\nnoop").unwrap();
+        let mut crt = CRT::new(Cpu::new(program), 4, 2, 1);
+        crt.iterate_commands_pixels();
+        assert_eq!(".#..\n...#", crt.text());
+        assert_eq!(" ▀ ▄\n", crt.ansi());
+        assert_eq!(b"P4\n4 2\n\x40\x10".to_vec(), crt.pbm());
+    }
 }
@@ -1,9 +1,19 @@
-use std::collections::VecDeque;
+use std::{
+    collections::VecDeque,
+    fmt::{self, Display},
+    fs,
+    io::{self, Write},
+    ops::{Add, Div, Mul, Neg, Sub},
+};
 
 use regex::Regex;
 
 use crate::{
+    bigint::BigInt,
+    cli::Args,
+    cycle::{find_cycle, split_steps},
     input::get_input,
+    problem::Problem,
     solution::{Solution, SolutionPair},
 };
 
@@ -15,157 +25,532 @@ pub fn solve() -> SolutionPair {
 }
 
 fn solve1(input: &str) -> Solution {
-    do_the_monkey_business(input, 20, true).into()
+    Solution::U64(do_the_monkey_business(input, 20, true).try_into().unwrap())
 }
 
 fn solve2(input: &str) -> Solution {
-    do_the_monkey_business(input, 10000, false).into()
+    Solution::U64(
+        do_the_monkey_business(input, 10000, false)
+            .try_into()
+            .unwrap(),
+    )
+}
+
+pub fn tool(args: &Args) {
+    let input = args.input(DAY);
+    let num_rounds = args.value("rounds").unwrap_or(10000);
+    let worry_divide = args.flag("divide");
+    let monkeys = match parse_monkeys(&input) {
+        Ok(monkeys) => monkeys,
+        Err(problems) => {
+            for problem in problems {
+                println!("{}", problem);
+            }
+            return;
+        }
+    };
+    if args.flag("check") {
+        println!("ok");
+        return;
+    }
+    if args.flag("dot") {
+        print!("{}", dot(&monkeys));
+        return;
+    }
+    let csv = args
+        .value::<String>("csv")
+        .map(|path| fs::File::create(path).unwrap());
+    if !args.flag("trace") && csv.is_none() {
+        println!(
+            "monkey business after {} rounds: {}",
+            num_rounds,
+            monkey_business(&monkeys, num_rounds, worry_divide)
+        );
+        return;
+    }
+    let mut tracer = Tracer {
+        monkeys: &monkeys,
+        prose: args.flag("trace").then(io::stdout),
+        csv,
+    };
+    match WorryMode::choose(&monkeys, worry_divide) {
+        WorryMode::Modulus(magic_number) => simulate::<i64>(
+            &monkeys,
+            num_rounds,
+            worry_divide,
+            Some(magic_number),
+            &mut tracer,
+        ),
+        WorryMode::Exact => {
+            simulate::<BigInt>(&monkeys, num_rounds, worry_divide, None, &mut tracer)
+        }
+    };
+}
+
+fn do_the_monkey_business(input: &str, num_rounds: u64, worry_divide: bool) -> u128 {
+    monkey_business(&parse_input(input), num_rounds, worry_divide)
+}
+
+fn monkey_business(monkeys: &[Monkey], num_rounds: u64, worry_divide: bool) -> u128 {
+    let mut inspection_counter = match WorryMode::choose(monkeys, worry_divide) {
+        WorryMode::Modulus(magic_number) => fast_forward(monkeys, num_rounds, magic_number),
+        WorryMode::Exact => simulate::<BigInt>(monkeys, num_rounds, worry_divide, None, &mut ()),
+    };
+    inspection_counter.sort();
+    inspection_counter[inspection_counter.len() - 2] as u128
+        * inspection_counter[inspection_counter.len() - 1] as u128
 }
 
-fn do_the_monkey_business(input: &str, num_rounds: i32, worry_divide: bool) -> u64 {
-    let mut monkeys = parse_input(input);
-    let mut inspection_counter: Vec<u64> = monkeys.iter().map(|_| 0).collect();
-    let magic_number: u64 = monkeys.iter().map(|m| m.test_details.0).product();
-    for _ in 0..num_rounds {
-        for monkey_index in 0..monkeys.len() {
-            while let Some(item) = monkeys.get_mut(monkey_index).unwrap().items.pop_front() {
+fn simulate<W: Worry>(
+    monkeys: &[Monkey],
+    num_rounds: u64,
+    worry_divide: bool,
+    modulus: Option<i64>,
+    observer: &mut impl Observer<W>,
+) -> Vec<u64> {
+    let mut items: Vec<VecDeque<W>> = monkeys
+        .iter()
+        .map(|m| m.items.iter().map(|&item| W::from_i64(item)).collect())
+        .collect();
+    let mut inspection_counter = vec![0; monkeys.len()];
+    for round in 1..=num_rounds {
+        let round_start = inspection_counter.clone();
+        for (monkey_index, monkey) in monkeys.iter().enumerate() {
+            observer.turn(monkey_index);
+            while let Some(item) = items[monkey_index].pop_front() {
                 inspection_counter[monkey_index] += 1;
-                let monkey = monkeys.get_mut(monkey_index).unwrap();
-                let mut new_worry_level = (monkey.operation)(monkey, item);
-                if worry_divide {
-                    new_worry_level = new_worry_level.div_floor(3);
+                let operated = match modulus {
+                    Some(modulus) => {
+                        W::from_i64(monkey.operation.eval_mod(item.rem(modulus), modulus))
+                    }
+                    None => monkey.operation.eval(&item),
+                };
+                let bored = worry_divide.then(|| operated.clone().floor_div(3));
+                let mut new_worry_level = bored.clone().unwrap_or_else(|| operated.clone());
+                if let Some(modulus) = modulus {
+                    new_worry_level = W::from_i64(new_worry_level.rem(modulus));
                 }
-                new_worry_level %= magic_number;
-                let destination_monkey = (monkey.test)(&monkey, new_worry_level);
-                monkeys
-                    .get_mut(destination_monkey as usize)
-                    .unwrap()
-                    .items
-                    .push_back(new_worry_level);
+                let divisible = new_worry_level.rem(monkey.test_details.0) == 0;
+                let destination = monkey.target(divisible);
+                observer.inspection(Inspection {
+                    monkey: monkey_index,
+                    old: &item,
+                    operated: &operated,
+                    bored: bored.as_ref(),
+                    new: &new_worry_level,
+                    divisible,
+                    destination,
+                });
+                items[destination as usize].push_back(new_worry_level);
             }
         }
+        let counts: Vec<_> = inspection_counter
+            .iter()
+            .zip(round_start)
+            .map(|(total, start)| total - start)
+            .collect();
+        observer.round(round, &counts);
     }
-    inspection_counter.sort();
-    (inspection_counter[inspection_counter.len() - 2]
-        * inspection_counter[inspection_counter.len() - 1])
-        .into()
+    inspection_counter
 }
 
-fn parse_input(input: &str) -> Vec<Monkey> {
-    let monkeys = input
-        .split("\n\n")
-        .map(|monkey| {
-            let regex = Regex::new(
-                r"Monkey (?P<index>[0-9]+):.*
-\s*Starting items: (?P<items>[0-9, ]+).*
-\s*Operation: new = (?P<operation>.*).*
-\s*Test: divisible by (?P<divisor>[0-9]+).*
-\s*  If true: throw to monkey (?P<true_monkey>[0-9]+).*
-\s*  If false: throw to monkey (?P<false_monkey>[0-9]+).*",
+struct Inspection<'a, W> {
+    monkey: usize,
+    old: &'a W,
+    operated: &'a W,
+    bored: Option<&'a W>,
+    new: &'a W,
+    divisible: bool,
+    destination: i32,
+}
+
+trait Observer<W> {
+    fn turn(&mut self, _monkey: usize) {}
+    fn inspection(&mut self, _inspection: Inspection<W>) {}
+    fn round(&mut self, _round: u64, _inspections: &[u64]) {}
+}
+
+impl<W> Observer<W> for () {}
+
+// writes inspections in the puzzle's own wording and the per round counts as csv
+struct Tracer<'a, P: Write, C: Write> {
+    monkeys: &'a [Monkey],
+    prose: Option<P>,
+    csv: Option<C>,
+}
+
+impl<W: Worry, P: Write, C: Write> Observer<W> for Tracer<'_, P, C> {
+    fn turn(&mut self, monkey: usize) {
+        if let Some(out) = &mut self.prose {
+            writeln!(out, "Monkey {}:", monkey).unwrap();
+        }
+    }
+
+    fn inspection(&mut self, inspection: Inspection<W>) {
+        let Some(out) = &mut self.prose else {
+            return;
+        };
+        let divisor = self.monkeys[inspection.monkey].test_details.0;
+        let operation = &self.monkeys[inspection.monkey].operation;
+        writeln!(
+            out,
+            "  Monkey inspects an item with a worry level of {}.",
+            inspection.old
+        )
+        .unwrap();
+        writeln!(out, "    {}", operation.describe(inspection.operated)).unwrap();
+        if let Some(bored) = inspection.bored {
+            writeln!(
+                out,
+                "    Monkey gets bored with item. Worry level is divided by 3 to {}.",
+                bored
             )
             .unwrap();
-            let matches = regex.captures(monkey).unwrap();
-            let operation_str = matches
-                .name("operation")
-                .map(|operation| operation.as_str().to_string())
-                .unwrap();
-            let test_details = (
-                matches.name("divisor").unwrap().as_str().parse().unwrap(),
-                matches
-                    .name("true_monkey")
-                    .unwrap()
-                    .as_str()
-                    .parse()
-                    .unwrap(),
-                matches
-                    .name("false_monkey")
-                    .unwrap()
-                    .as_str()
-                    .parse()
-                    .unwrap(),
+        }
+        writeln!(
+            out,
+            "    Current worry level {} divisible by {}.",
+            if inspection.divisible { "is" } else { "is not" },
+            divisor
+        )
+        .unwrap();
+        writeln!(
+            out,
+            "    Item with worry level {} is thrown to monkey {}.",
+            inspection.new, inspection.destination
+        )
+        .unwrap();
+    }
+
+    fn round(&mut self, round: u64, inspections: &[u64]) {
+        let Some(out) = &mut self.csv else {
+            return;
+        };
+        if round == 1 {
+            let header: Vec<_> = (0..inspections.len())
+                .map(|i| format!("monkey {}", i))
+                .collect();
+            writeln!(out, "round,{}", header.join(",")).unwrap();
+        }
+        let counts: Vec<_> = inspections.iter().map(|c| c.to_string()).collect();
+        writeln!(out, "{},{}", round, counts.join(",")).unwrap();
+    }
+}
+
+fn dot(monkeys: &[Monkey]) -> String {
+    let mut out = "digraph monkeys {\n".to_string();
+    for (i, monkey) in monkeys.iter().enumerate() {
+        let (divisor, true_monkey, false_monkey) = monkey.test_details;
+        out.push_str(&format!(
+            "  {} [label=\"Monkey {}\\nnew = {}\"];\n",
+            i, i, monkey.operation
+        ));
+        out.push_str(&format!(
+            "  {} -> {} [label=\"divisible by {}\"];\n",
+            i, true_monkey, divisor
+        ));
+        out.push_str(&format!(
+            "  {} -> {} [label=\"not divisible by {}\", style=dashed];\n",
+            i, false_monkey, divisor
+        ));
+    }
+    out.push_str("}\n");
+    out
+}
+
+// items never interact, so with bounded worry levels every item's journey through the
+// monkeys eventually repeats and whole cycles of rounds can be skipped
+fn fast_forward(monkeys: &[Monkey], num_rounds: u64, modulus: i64) -> Vec<u64> {
+    let mut inspection_counter = vec![0; monkeys.len()];
+    let mut scratch = vec![0; monkeys.len()];
+    // a search that needs more rounds than are asked for costs more than just playing them
+    let search_limit = usize::try_from(num_rounds).unwrap_or(usize::MAX);
+    for (monkey_index, monkey) in monkeys.iter().enumerate() {
+        for &item in &monkey.items {
+            let start = (monkey_index, item.rem_euclid(modulus));
+            let found = find_cycle(start, search_limit, |&state| {
+                item_round(monkeys, modulus, state, &mut scratch)
+            });
+            let Some((mu, lambda)) = found else {
+                let mut state = start;
+                for _ in 0..num_rounds {
+                    state = item_round(monkeys, modulus, state, &mut inspection_counter);
+                }
+                continue;
+            };
+            let (lead_in, cycles, leftover) = split_steps(num_rounds, mu, lambda);
+            let mut state = start;
+            for _ in 0..lead_in {
+                state = item_round(monkeys, modulus, state, &mut inspection_counter);
+            }
+            let mut cycle_counter = vec![0; monkeys.len()];
+            if cycles > 0 {
+                for _ in 0..lambda {
+                    state = item_round(monkeys, modulus, state, &mut cycle_counter);
+                }
+            }
+            for (total, per_cycle) in inspection_counter.iter_mut().zip(cycle_counter) {
+                *total += per_cycle * cycles;
+            }
+            for _ in 0..leftover {
+                state = item_round(monkeys, modulus, state, &mut inspection_counter);
+            }
+        }
+    }
+    inspection_counter
+}
+
+// one round for a single item held by `monkey`, it keeps moving while thrown to monkeys
+// that have not had their turn yet
+fn item_round(
+    monkeys: &[Monkey],
+    modulus: i64,
+    (mut monkey_index, mut worry): (usize, i64),
+    inspection_counter: &mut [u64],
+) -> (usize, i64) {
+    loop {
+        let monkey = &monkeys[monkey_index];
+        inspection_counter[monkey_index] += 1;
+        worry = monkey.operation.eval_mod(worry, modulus);
+        let target = monkey.target(worry % monkey.test_details.0 == 0) as usize;
+        if target <= monkey_index {
+            return (target, worry);
+        }
+        monkey_index = target;
+    }
+}
+
+trait Worry:
+    Clone
+    + Display
+    + Add<Output = Self>
+    + Sub<Output = Self>
+    + Mul<Output = Self>
+    + Div<Output = Self>
+    + Neg<Output = Self>
+{
+    fn from_i64(v: i64) -> Self;
+    fn floor_div(self, divisor: i64) -> Self;
+    fn rem(&self, divisor: i64) -> i64;
+}
+
+impl Worry for i64 {
+    fn from_i64(v: i64) -> Self {
+        v
+    }
+
+    fn floor_div(self, divisor: i64) -> Self {
+        self.div_floor(divisor)
+    }
+
+    fn rem(&self, divisor: i64) -> i64 {
+        self.rem_euclid(divisor)
+    }
+}
+
+impl Worry for BigInt {
+    fn from_i64(v: i64) -> Self {
+        BigInt::from_i64(v)
+    }
+
+    fn floor_div(self, divisor: i64) -> Self {
+        self.div_floor(&BigInt::from_i64(divisor))
+    }
+
+    fn rem(&self, divisor: i64) -> i64 {
+        self.rem_euclid_i64(divisor)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum WorryMode {
+    Modulus(i64),
+    Exact,
+}
+
+impl WorryMode {
+    // reducing modulo the product of the divisors only keeps the tests intact through
+    // addition, subtraction and multiplication, any division needs the exact value
+    fn choose(monkeys: &[Monkey], worry_divide: bool) -> Self {
+        if worry_divide || monkeys.iter().any(|m| m.operation.divides()) {
+            return WorryMode::Exact;
+        }
+        let product = monkeys
+            .iter()
+            .try_fold(1i64, |product, m| product.checked_mul(m.test_details.0));
+        match product {
+            Some(modulus) => WorryMode::Modulus(modulus),
+            None => WorryMode::Exact,
+        }
+    }
+}
+
+fn parse_input(input: &str) -> Vec<Monkey> {
+    parse_monkeys(input).unwrap_or_else(|problems| {
+        let report: Vec<_> = problems.iter().map(|p| p.to_string()).collect();
+        panic!("invalid monkey definitions:\n{}", report.join("\n"));
+    })
+}
+
+// checks every monkey definition line by line and reports all problems at once
+fn parse_monkeys(input: &str) -> Result<Vec<Monkey>, Vec<Problem>> {
+    let expected_lines = [
+        ("Monkey <n>:", r"^Monkey (\d+):\s*$"),
+        ("Starting items: <items>", r"^\s*Starting items:(.*)$"),
+        (
+            "Operation: new = <expression>",
+            r"^\s*Operation: new = (.*)$",
+        ),
+        (
+            "Test: divisible by <n>",
+            r"^\s*Test: divisible by (\d+)\s*$",
+        ),
+        (
+            "If true: throw to monkey <n>",
+            r"^\s*If true: throw to monkey (\d+)\s*$",
+        ),
+        (
+            "If false: throw to monkey <n>",
+            r"^\s*If false: throw to monkey (\d+)\s*$",
+        ),
+    ]
+    .map(|(description, pattern)| (description, Regex::new(pattern).unwrap()));
+
+    let mut blocks: Vec<Vec<(usize, &str)>> = vec![vec![]];
+    for (i, line) in input.lines().enumerate() {
+        if line.trim().is_empty() {
+            if !blocks.last().unwrap().is_empty() {
+                blocks.push(vec![]);
+            }
+        } else {
+            blocks.last_mut().unwrap().push((i + 1, line));
+        }
+    }
+    if blocks.last().unwrap().is_empty() {
+        blocks.pop();
+    }
+
+    let mut problems = vec![];
+    let mut problem = |line: usize, message: String| problems.push(Problem::new(line, message));
+    match &blocks[..] {
+        [] => problem(1, "no monkeys defined".into()),
+        [block] => problem(
+            block[0].0,
+            "monkey business needs at least two monkeys".into(),
+        ),
+        _ => {}
+    }
+    let mut throws = vec![];
+    let mut monkeys = vec![];
+    for (position, block) in blocks.iter().enumerate() {
+        let mut items = VecDeque::new();
+        let (mut operation, mut divisor, mut targets) = (None, None, vec![]);
+        if block.len() != expected_lines.len() {
+            problem(
+                block[0].0,
+                format!(
+                    "monkey definition has {} lines, expected {}",
+                    block.len(),
+                    expected_lines.len()
+                ),
             );
-            Monkey {
-                items: matches
-                    .name("items")
-                    .map(|items| {
-                        items
-                            .as_str()
-                            .split(", ")
-                            .map(|item| item.parse().unwrap())
-                            .collect()
-                    })
-                    .unwrap(),
-                operation_str,
-                operation_details: None,
-                operation: |monkey: &mut Monkey, old: u64| {
-                    let (val1, operator, val2) = match &monkey.operation_details {
-                        Some(operation_details) => *operation_details,
-                        None => {
-                            let portions: Vec<_> = monkey.operation_str.split(" ").collect();
-                            let operator_str = portions[1];
-                            let val1 = if portions[0] == "old" {
-                                Operand::Old
-                            } else {
-                                Operand::Val(portions[0].parse::<u64>().unwrap())
-                            };
-                            let val2 = if portions[2] == "old" {
-                                Operand::Old
-                            } else {
-                                Operand::Val(portions[2].parse::<u64>().unwrap())
-                            };
-                            let operator = match operator_str {
-                                "*" => Operator::Mul,
-                                "/" => Operator::Div,
-                                "+" => Operator::Add,
-                                "-" => Operator::Sub,
-                                _ => panic!("Unknown operator {}", operator_str),
-                            };
-                            monkey.operation_details = Some((val1, operator, val2));
-                            (val1, operator, val2)
+        }
+        for (&(line, text), (i, (description, regex))) in
+            block.iter().zip(expected_lines.iter().enumerate())
+        {
+            let Some(captures) = regex.captures(text) else {
+                problem(
+                    line,
+                    format!("expected \"{}\", found \"{}\"", description, text.trim()),
+                );
+                continue;
+            };
+            let value = captures.get(1).unwrap().as_str().trim();
+            match i {
+                0 => match value.parse::<usize>() {
+                    Ok(index) if index == position => {}
+                    _ => problem(
+                        line,
+                        format!("monkey headers out of order, expected Monkey {}", position),
+                    ),
+                },
+                1 => {
+                    for item in value
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|item| !item.is_empty())
+                    {
+                        match item.parse::<i64>() {
+                            Ok(item) => items.push_back(item),
+                            Err(_) => problem(line, format!("invalid starting item {}", item)),
                         }
-                    };
-
-                    let val1 = match val1 {
-                        Operand::Old => old,
-                        Operand::Val(v) => v,
-                    };
-                    let val2 = match val2 {
-                        Operand::Old => old,
-                        Operand::Val(v) => v,
-                    };
-                    match operator {
-                        Operator::Mul => val1 * val2,
-                        Operator::Div => val1 / val2,
-                        Operator::Add => val1 + val2,
-                        Operator::Sub => val1 - val2,
                     }
+                }
+                2 => match Expr::parse(value) {
+                    Ok(expr) => operation = Some(expr),
+                    Err(e) => problem(line, format!("invalid operation {}: {}", value, e)),
                 },
-                test_details,
-                test: |monkey: &Monkey, value: u64| {
-                    let (divisor, true_monkey, false_monkey) = &monkey.test_details;
-                    if value % divisor == 0 {
-                        *true_monkey
-                    } else {
-                        *false_monkey
-                    }
+                3 => match value.parse::<i64>() {
+                    Ok(0) => problem(line, "divisor must not be zero".into()),
+                    Ok(d) => divisor = Some(d),
+                    Err(_) => problem(line, format!("divisor {} is too large", value)),
                 },
+                _ => {
+                    let to = value.parse::<usize>().unwrap_or(usize::MAX);
+                    throws.push((line, position, to));
+                    targets.push(to as i32);
+                }
             }
-        })
-        .collect();
-    monkeys
+        }
+        // an incomplete monkey always comes with a problem, so it is never needed
+        if let (Some(operation), Some(divisor), &[true_monkey, false_monkey]) =
+            (operation, divisor, &targets[..])
+        {
+            monkeys.push(Monkey {
+                items,
+                operation,
+                test_details: (divisor, true_monkey, false_monkey),
+            });
+        }
+    }
+    for (line, from, to) in throws {
+        if to == from {
+            problem(line, format!("monkey {} throws to itself", from));
+        } else if to >= blocks.len() {
+            problem(
+                line,
+                format!("monkey {} throws to nonexistent monkey", from),
+            );
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(monkeys)
+    } else {
+        problems.sort_by_key(|p| p.line);
+        Err(problems)
+    }
 }
 
 #[derive(Clone)]
 struct Monkey {
-    items: VecDeque<u64>,
-    operation_str: String,
-    operation_details: Option<(Operand, Operator, Operand)>,
-    operation: fn(&mut Monkey, u64) -> u64,
-    test_details: (u64, i32, i32),
-    test: fn(&Monkey, u64) -> i32,
+    items: VecDeque<i64>,
+    operation: Expr,
+    test_details: (i64, i32, i32),
+}
+
+impl Monkey {
+    fn target(&self, divisible: bool) -> i32 {
+        let (_, true_monkey, false_monkey) = self.test_details;
+        if divisible {
+            true_monkey
+        } else {
+            false_monkey
+        }
+    }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 enum Operator {
     Mul,
     Div,
@@ -173,17 +558,247 @@ enum Operator {
     Sub,
 }
 
-#[derive(Clone, Copy)]
-enum Operand {
+impl Operator {
+    fn precedence(&self) -> u8 {
+        match self {
+            Operator::Add | Operator::Sub => 1,
+            Operator::Mul | Operator::Div => 2,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Expr {
+    Old,
+    Val(i64),
+    Neg(Box<Expr>),
+    BinOp(Box<Expr>, Operator, Box<Expr>),
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Old => write!(f, "old"),
+            Expr::Val(v) => write!(f, "{}", v),
+            Expr::Neg(e) => match **e {
+                Expr::BinOp(..) => write!(f, "-({})", e),
+                _ => write!(f, "-{}", e),
+            },
+            Expr::BinOp(lhs, op, rhs) => {
+                // operators are left associative, so an equal precedence right operand needs parentheses
+                let wrap = |e: &Expr, right: bool| match e {
+                    Expr::BinOp(_, inner, _) => {
+                        inner.precedence() < op.precedence()
+                            || (right && inner.precedence() == op.precedence())
+                    }
+                    _ => false,
+                };
+                let symbol = match op {
+                    Operator::Mul => '*',
+                    Operator::Div => '/',
+                    Operator::Add => '+',
+                    Operator::Sub => '-',
+                };
+                if wrap(lhs, false) {
+                    write!(f, "({})", lhs)?;
+                } else {
+                    write!(f, "{}", lhs)?;
+                }
+                write!(f, " {} ", symbol)?;
+                if wrap(rhs, true) {
+                    write!(f, "({})", rhs)
+                } else {
+                    write!(f, "{}", rhs)
+                }
+            }
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Token {
     Old,
-    Val(u64),
+    Val(i64),
+    Op(Operator),
+    Open,
+    Close,
+}
+
+impl Expr {
+    fn parse(input: &str) -> Result<Self, String> {
+        let tokens = Self::tokenize(input)?;
+        let mut position = 0;
+        let expr = Self::parse_binary(&tokens, &mut position, 0)?;
+        match tokens.get(position) {
+            None => Ok(expr),
+            Some(token) => Err(format!("unexpected {:?}", token)),
+        }
+    }
+
+    fn tokenize(input: &str) -> Result<Vec<Token>, String> {
+        let mut tokens = vec![];
+        let mut chars = input.chars().peekable();
+        while let Some(&c) = chars.peek() {
+            let token = match c {
+                ' ' => {
+                    chars.next();
+                    continue;
+                }
+                '+' => Token::Op(Operator::Add),
+                '-' => Token::Op(Operator::Sub),
+                '*' => Token::Op(Operator::Mul),
+                '/' => Token::Op(Operator::Div),
+                '(' => Token::Open,
+                ')' => Token::Close,
+                '0'..='9' => {
+                    let mut digits = String::new();
+                    while let Some(d) = chars.next_if(|d| d.is_ascii_digit()) {
+                        digits.push(d);
+                    }
+                    tokens.push(Token::Val(
+                        digits
+                            .parse()
+                            .map_err(|_| format!("{} is too large", digits))?,
+                    ));
+                    continue;
+                }
+                _ => {
+                    let mut word = String::new();
+                    while let Some(w) = chars.next_if(|w| w.is_alphanumeric()) {
+                        word.push(w);
+                    }
+                    match word.as_str() {
+                        "old" => tokens.push(Token::Old),
+                        "" => return Err(format!("unexpected character {}", c)),
+                        _ => return Err(format!("unknown identifier {}", word)),
+                    }
+                    continue;
+                }
+            };
+            chars.next();
+            tokens.push(token);
+        }
+        Ok(tokens)
+    }
+
+    // precedence climbing, every operator is left associative
+    fn parse_binary(
+        tokens: &[Token],
+        position: &mut usize,
+        min_precedence: u8,
+    ) -> Result<Self, String> {
+        let mut lhs = Self::parse_unary(tokens, position)?;
+        while let Some(Token::Op(op)) = tokens.get(*position) {
+            if op.precedence() < min_precedence {
+                break;
+            }
+            *position += 1;
+            let rhs = Self::parse_binary(tokens, position, op.precedence() + 1)?;
+            lhs = Expr::BinOp(Box::new(lhs), *op, Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn parse_unary(tokens: &[Token], position: &mut usize) -> Result<Self, String> {
+        let token = tokens
+            .get(*position)
+            .ok_or("unexpected end of expression")?;
+        *position += 1;
+        match token {
+            Token::Old => Ok(Expr::Old),
+            Token::Val(v) => Ok(Expr::Val(*v)),
+            Token::Op(Operator::Sub) => {
+                Ok(Expr::Neg(Box::new(Self::parse_unary(tokens, position)?)))
+            }
+            Token::Open => {
+                let expr = Self::parse_binary(tokens, position, 0)?;
+                match tokens.get(*position) {
+                    Some(Token::Close) => {
+                        *position += 1;
+                        Ok(expr)
+                    }
+                    _ => Err("missing closing parenthesis".into()),
+                }
+            }
+            _ => Err(format!("unexpected {:?}", token)),
+        }
+    }
+
+    // the puzzle's phrasing for the simple operations it uses, anything else is spelled out
+    fn describe(&self, result: &impl Display) -> String {
+        let change = match self {
+            Expr::BinOp(lhs, op, rhs) if **lhs == Expr::Old => match (op, &**rhs) {
+                (Operator::Mul, Expr::Old) => Some("is multiplied by itself".to_string()),
+                (Operator::Add, Expr::Old) => Some("increases by itself".to_string()),
+                (Operator::Mul, Expr::Val(v)) => Some(format!("is multiplied by {}", v)),
+                (Operator::Div, Expr::Val(v)) => Some(format!("is divided by {}", v)),
+                (Operator::Add, Expr::Val(v)) => Some(format!("increases by {}", v)),
+                (Operator::Sub, Expr::Val(v)) => Some(format!("decreases by {}", v)),
+                _ => None,
+            },
+            _ => None,
+        };
+        match change {
+            Some(change) => format!("Worry level {} to {}.", change, result),
+            None => format!("Worry level becomes {} = {}.", self, result),
+        }
+    }
+
+    fn divides(&self) -> bool {
+        match self {
+            Expr::Old | Expr::Val(_) => false,
+            Expr::Neg(e) => e.divides(),
+            Expr::BinOp(lhs, op, rhs) => *op == Operator::Div || lhs.divides() || rhs.divides(),
+        }
+    }
+
+    // reduces after every operation with room for the product of two residues, so no
+    // power of old can overflow
+    fn eval_mod(&self, old: i64, modulus: i64) -> i64 {
+        let value = match self {
+            Expr::Old => old as i128,
+            Expr::Val(v) => *v as i128,
+            Expr::Neg(e) => -(e.eval_mod(old, modulus) as i128),
+            Expr::BinOp(lhs, op, rhs) => {
+                let lhs = lhs.eval_mod(old, modulus) as i128;
+                let rhs = rhs.eval_mod(old, modulus) as i128;
+                match op {
+                    Operator::Mul => lhs * rhs,
+                    Operator::Add => lhs + rhs,
+                    Operator::Sub => lhs - rhs,
+                    Operator::Div => unreachable!("division needs exact worry levels"),
+                }
+            }
+        };
+        value.rem_euclid(modulus as i128) as i64
+    }
+
+    fn eval<W: Worry>(&self, old: &W) -> W {
+        match self {
+            Expr::Old => old.clone(),
+            Expr::Val(v) => W::from_i64(*v),
+            Expr::Neg(e) => -e.eval(old),
+            Expr::BinOp(lhs, op, rhs) => {
+                let (lhs, rhs) = (lhs.eval(old), rhs.eval(old));
+                match op {
+                    Operator::Mul => lhs * rhs,
+                    Operator::Div => lhs / rhs,
+                    Operator::Add => lhs + rhs,
+                    Operator::Sub => lhs - rhs,
+                }
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{input::get_input, solution::Solution};
+    use crate::{bigint::BigInt, input::get_input, solution::Solution};
 
-    use super::{solve1, solve2, DAY};
+    use super::{
+        do_the_monkey_business, dot, fast_forward, parse_input, parse_monkeys, simulate, solve1,
+        solve2, Expr, Problem, Tracer, WorryMode, DAY,
+    };
 
     fn sample_input() -> String {
         get_input(DAY, true, None)
@@ -198,4 +813,233 @@ mod test {
     fn sample_2() {
         assert_eq!(Solution::U64(2713310158), solve2(&sample_input()));
     }
+
+    #[test]
+    fn expressions() {
+        let eval = |input: &str, old: i64| Expr::parse(input).unwrap().eval(&old);
+        assert_eq!(38, eval("old * 19", 2));
+        assert_eq!(14, eval("2 + 3 * old", 4));
+        assert_eq!(56, eval("(old * 3 + 2) * old", 4));
+        assert_eq!(-7, eval("-old - 3", 4));
+        assert_eq!(2, eval("old - old / 2 - 1", 6));
+        assert_eq!(16, eval("-(-old) * --old", 4));
+        assert!(Expr::parse("old *").is_err());
+        assert!(Expr::parse("(old + 1").is_err());
+        assert!(Expr::parse("old + x").is_err());
+        assert!(Expr::parse("old 3").is_err());
+    }
+
+    #[test]
+    fn custom_operation() {
+        let input = sample_input().replace("new = old * 19", "new = (old * 3 + 2) * old");
+        assert_eq!(24999999, do_the_monkey_business(&input, 1000, false));
+    }
+
+    #[test]
+    fn worry_mode() {
+        let choose =
+            |input: &str, worry_divide| WorryMode::choose(&parse_input(input), worry_divide);
+        assert_eq!(WorryMode::Modulus(96577), choose(&sample_input(), false));
+        assert_eq!(WorryMode::Exact, choose(&sample_input(), true));
+        let dividing = sample_input().replace("new = old + 6", "new = (old - 1) / 2 * 5");
+        assert_eq!(WorryMode::Exact, choose(&dividing, false));
+        let huge_divisors = sample_input()
+            .replace("divisible by 23", "divisible by 4000000007")
+            .replace("divisible by 19", "divisible by 4000000009");
+        assert_eq!(WorryMode::Exact, choose(&huge_divisors, false));
+    }
+
+    #[test]
+    fn cubic_operation() {
+        let input = get_input(DAY, false, None).replace("new = old * old", "new = old * old * old");
+        let monkeys = parse_input(&input);
+        let WorryMode::Modulus(magic_number) = WorryMode::choose(&monkeys, false) else {
+            panic!("expected modulus mode");
+        };
+        // the cube of a residue does not fit in an i64, exact levels show the reduction is sound
+        assert_eq!(
+            simulate::<BigInt>(&monkeys, 3, false, None, &mut ()),
+            simulate::<i64>(&monkeys, 3, false, Some(magic_number), &mut ())
+        );
+        assert_eq!(
+            simulate::<i64>(&monkeys, 10000, false, Some(magic_number), &mut ()),
+            fast_forward(&monkeys, 10000, magic_number)
+        );
+    }
+
+    #[test]
+    fn exact_worry_levels() {
+        let input = sample_input()
+            .replace("new = old + 6", "new = (old - 1) / 2 * 5")
+            .replace("new = old * old", "new = old * old - 3 * old");
+        assert_eq!(22499, do_the_monkey_business(&input, 30, false));
+    }
+
+    #[test]
+    fn fast_forward_matches_simulation() {
+        let monkeys = parse_input(&sample_input());
+        let magic_number = monkeys.iter().map(|m| m.test_details.0).product();
+        for num_rounds in [0, 1, 20, 1000, 10000] {
+            assert_eq!(
+                simulate::<i64>(&monkeys, num_rounds, false, Some(magic_number), &mut ()),
+                fast_forward(&monkeys, num_rounds, magic_number)
+            );
+        }
+    }
+
+    #[test]
+    fn huge_round_count() {
+        let monkeys = parse_input(&sample_input());
+        let magic_number = monkeys.iter().map(|m| m.test_details.0).product();
+        assert_eq!(
+            vec![5217653496, 4782346500, 193256586, 5202028499],
+            fast_forward(&monkeys, 1_000_000_000, magic_number)
+        );
+        assert_eq!(
+            27142382184098982504,
+            do_the_monkey_business(&sample_input(), 1_000_000_000, false)
+        );
+    }
+
+    #[test]
+    fn expression_display_round_trip() {
+        for input in [
+            "old * 19",
+            "(old * 3 + 2) * old",
+            "old - (old - 1)",
+            "-(old + 1) / 2",
+            "old * -old",
+        ] {
+            let expr = Expr::parse(input).unwrap();
+            assert_eq!(input, expr.to_string());
+            assert_eq!(expr, Expr::parse(&expr.to_string()).unwrap());
+        }
+    }
+
+    #[test]
+    fn trace() {
+        let monkeys = parse_input(&sample_input());
+        let mut prose = vec![];
+        let mut csv = vec![];
+        let mut tracer = Tracer {
+            monkeys: &monkeys,
+            prose: Some(&mut prose),
+            csv: Some(&mut csv),
+        };
+        simulate::<i64>(&monkeys, 2, true, None, &mut tracer);
+        let prose = String::from_utf8(prose).unwrap();
+        assert!(prose.starts_with(
+            "Monkey 0:
+  Monkey inspects an item with a worry level of 79.
+    Worry level is multiplied by 19 to 1501.
+    Monkey gets bored with item. Worry level is divided by 3 to 500.
+    Current worry level is not divisible by 23.
+    Item with worry level 500 is thrown to monkey 3.
+"
+        ));
+        assert!(prose.contains(
+            "  Monkey inspects an item with a worry level of 79.
+    Worry level is multiplied by itself to 6241.
+    Monkey gets bored with item. Worry level is divided by 3 to 2080.
+    Current worry level is divisible by 13.
+    Item with worry level 2080 is thrown to monkey 1.
+"
+        ));
+        assert_eq!(
+            "round,monkey 0,monkey 1,monkey 2,monkey 3\n1,2,4,3,5\n2,4,6,1,5\n",
+            String::from_utf8(csv).unwrap()
+        );
+    }
+
+    #[test]
+    fn dot_graph() {
+        let graph = dot(&parse_input(&sample_input()));
+        assert!(
+            graph.starts_with("digraph monkeys {\n  0 [label=\"Monkey 0\\nnew = old * 19\"];\n")
+        );
+        assert!(graph.contains("  2 -> 1 [label=\"divisible by 13\"];\n"));
+        assert!(graph.contains("  3 -> 1 [label=\"not divisible by 17\", style=dashed];\n"));
+        assert!(graph.ends_with("}\n"));
+    }
+
+    #[test]
+    fn validate_sample() {
+        assert!(parse_monkeys(&sample_input()).is_ok());
+    }
+
+    #[test]
+    fn unusual_layout() {
+        // the product of the sample's divisors, an item equal modulo it gives the same part 2
+        let modulus = 23 * 19 * 13 * 17;
+        let input = sample_input()
+            .trim_end()
+            .replace("items: 79,", &format!("items: {},", 79 - modulus))
+            .replace("\n\n", "\n  \t\n")
+            .replace('\n', "\r\n")
+            + "\r\n\r\nMonkey 4:\r\n  Starting items:\r\n  Operation: new = old\r\n  Test: divisible by 1\r\n    If true: throw to monkey 0\r\n    If false: throw to monkey 0\r\n";
+        let monkeys = parse_monkeys(&input).unwrap();
+        assert_eq!(5, monkeys.len());
+        assert!(monkeys[4].items.is_empty());
+        assert_eq!(solve2(&sample_input()), solve2(&input));
+    }
+
+    #[test]
+    fn validate_reports_every_problem() {
+        let input = sample_input()
+            .replace("Monkey 1:", "Monkey 2:")
+            .replace("new = old + 6", "new = old + ")
+            .replace(
+                "If false: throw to monkey 3\n\nMonkey 2",
+                "If false: throw to monkey 7\n\nMonkey 2",
+            )
+            .replace(
+                "divisible by 17\n    If true: throw to monkey 0",
+                "divisible by 0\n    If true: throw to monkey 3",
+            )
+            .replace("Starting items: 74", "Starting items: 74, x");
+        assert_eq!(
+            Err(vec![
+                Problem::new(6, "monkey 0 throws to nonexistent monkey"),
+                Problem::new(8, "monkey headers out of order, expected Monkey 1"),
+                Problem::new(10, "invalid operation old +: unexpected end of expression"),
+                Problem::new(23, "invalid starting item x"),
+                Problem::new(25, "divisor must not be zero"),
+                Problem::new(26, "monkey 3 throws to itself"),
+            ]),
+            parse_monkeys(&input).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn validate_too_few_monkeys() {
+        assert_eq!(
+            Err(vec![Problem::new(1, "no monkeys defined")]),
+            parse_monkeys("").map(|_| ())
+        );
+        assert_eq!(
+            Err(vec![Problem::new(1, "no monkeys defined")]),
+            parse_monkeys("\n  \n\n").map(|_| ())
+        );
+        let input = "Monkey 0:\n  Starting items: 1\n  Operation: new = old\n  Test: divisible by 2\n    If true: throw to monkey 1\n    If false: throw to monkey 1\n";
+        assert_eq!(
+            Err(vec![
+                Problem::new(1, "monkey business needs at least two monkeys"),
+                Problem::new(5, "monkey 0 throws to nonexistent monkey"),
+                Problem::new(6, "monkey 0 throws to nonexistent monkey"),
+            ]),
+            parse_monkeys(input).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn validate_layout() {
+        let input = "Monkey 0:\n  Starting items: 1\n  Operation: new = old\n  Test: divisible by 2\n    If true: throw to monkey 1\n\nMonkey 1:\n  Starting items: 1\n  Operation: new = old\n  Test: divisible by 2\n    If true: throw to monkey 0\n    If false: throw to monkey 0\n    If false: throw to monkey 0\n";
+        assert_eq!(
+            Err(vec![
+                Problem::new(1, "monkey definition has 5 lines, expected 6"),
+                Problem::new(7, "monkey definition has 7 lines, expected 6"),
+            ]),
+            parse_monkeys(input).map(|_| ())
+        );
+    }
 }
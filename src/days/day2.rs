@@ -1,7 +1,13 @@
-use phf::phf_map;
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+    fs,
+};
 
 use crate::{
+    cli::Args,
     input::get_input,
+    problem::Problem,
     solution::{Solution, SolutionPair},
 };
 
@@ -11,162 +17,546 @@ pub fn solve() -> SolutionPair {
 }
 
 fn solve1(input: &str) -> Solution {
-    let final_score = get_split_input(input)
-        .iter()
-        .map(|(them, you)| Type1Scorer::score_round(them, you))
-        .sum();
-    Solution::I32(final_score)
+    let game = Game::rock_paper_scissors();
+    let score = total_score(&Type1Scorer { game: &game }, input);
+    Solution::I32(score.unwrap_or_else(|e| panic!("{}", e)))
 }
 
 fn solve2(input: &str) -> Solution {
-    let final_score = get_split_input(input)
-        .iter()
-        .map(|(them, you)| Type2Scorer::score_round(them, you))
-        .sum();
-    Solution::I32(final_score)
+    let game = Game::rock_paper_scissors();
+    let score = total_score(&Type2Scorer::new(&game).unwrap(), input);
+    Solution::I32(score.unwrap_or_else(|e| panic!("{}", e)))
 }
 
-fn get_lines(input: &str) -> Vec<&str> {
-    input.lines().collect()
+// `--rules path` plays the guide with a different game, see ROCK_PAPER_SCISSORS for the format
+pub fn tool(args: &Args) {
+    let game = match args.value::<String>("rules") {
+        Some(path) => {
+            let config =
+                fs::read_to_string(&path).unwrap_or_else(|e| panic!("cannot read {}: {}", path, e));
+            Game::parse(&config).unwrap_or_else(|problems| {
+                let report: Vec<_> = problems.iter().map(|p| p.to_string()).collect();
+                panic!("invalid rules in {}:\n{}", path, report.join("\n"))
+            })
+        }
+        None => Game::rock_paper_scissors(),
+    };
+    let input = args.input(2);
+    fn or_fail<T>(result: Result<T, Problem>) -> T {
+        result.unwrap_or_else(|e| panic!("{}", e))
+    }
+    let type1 = Type1Scorer { game: &game };
+    let type2 = Type2Scorer::new(&game);
+    let given = or_fail(total_score(&type1, &input));
+    println!("part 1: {}", given);
+    match &type2 {
+        Ok(scorer) => println!("part 2: {}", or_fail(total_score(scorer, &input))),
+        Err(e) => println!("part 2: {}", e),
+    }
+    if args.flag("analyse") {
+        println!("\npart 1\n{}", or_fail(analyse(&type1, &input)));
+        if let Ok(scorer) = &type2 {
+            println!("\npart 2\n{}", or_fail(analyse(scorer, &input)));
+        }
+    }
+    if args.flag("optimise") {
+        let optimal = or_fail(optimal_score(&game, &input));
+        println!("\nbest possible: {}", optimal);
+        println!("given guide: {} ({} short)", given, optimal - given);
+        for (reading, score) in or_fail(reinterpretations(&game, &input)) {
+            println!("{}: {} ({} short)", reading, score, optimal - score);
+        }
+    }
+}
+
+fn total_score<S: RoundScorer>(scorer: &S, input: &str) -> Result<i32, Problem> {
+    let mut total = 0;
+    for round in rounds(scorer.game(), input) {
+        let (line, them, you) = round?;
+        let you = scorer
+            .column(you)
+            .map_err(|message| Problem::new(line, message))?;
+        total += scorer.score_round(them, you);
+    }
+    Ok(total)
 }
 
-fn get_split_input(input: &str) -> Vec<(String, String)> {
-    get_lines(input)
-        .iter()
-        .map(|line| {
-            let pair: Vec<_> = line.split(" ").take(2).collect();
-            (pair[0].into(), pair[1].into())
+// the line number, opponent's shape and unparsed second column of each round in a guide
+fn rounds<'a>(
+    game: &'a Game,
+    input: &'a str,
+) -> impl Iterator<Item = Result<(usize, Shape, &'a str), Problem>> + 'a {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty())
+        .map(|(i, l)| {
+            let problem = |message| Problem::new(i + 1, message);
+            let mut symbols = l.split_whitespace();
+            match (symbols.next(), symbols.next(), symbols.next()) {
+                (Some(them), Some(you), None) => match game.shape(them) {
+                    Some(them) => Ok((i + 1, them, you)),
+                    None => Err(problem(format!("unknown shape {}", them))),
+                },
+                _ => Err(problem(format!("expected two symbols, got {}", l.trim()))),
+            }
         })
-        .collect()
 }
 
+// games define their own shapes, so this indexes `Game::shapes` rather than naming them
+#[derive(PartialEq, Eq, Debug, Copy, Clone, Hash)]
+struct Shape(usize);
+
 #[derive(PartialEq, Eq, Debug, Copy, Clone)]
-enum RoundOutcome {
-    Loss = 0,
-    Draw = 3,
-    Win = 6,
-}
-
-static PRETTY_SHAPE: phf::Map<&str, &str> = phf_map! {
-    "A" => "Rock",
-    "X" => "Rock",
-    "B" => "Paper",
-    "Y" => "Paper",
-    "C" => "Scissors",
-    "Z" => "Scissors",
-};
+enum Outcome {
+    Loss,
+    Draw,
+    Win,
+}
 
-static SHAPE_SCORES: phf::Map<&str, i32> = phf_map! {
-    "Rock" => 1,
-    "Paper" => 2,
-    "Scissors" => 3,
-};
+impl Outcome {
+    const ALL: [Outcome; 3] = [Outcome::Loss, Outcome::Draw, Outcome::Win];
+}
 
-static DEFEATS: phf::Map<&'static str, &'static str> = phf_map! {
-    "Rock" => "Scissors",
-    "Scissors" => "Paper",
-    "Paper" => "Rock",
-};
+// one definition per line, shape and outcome aliases are the symbols used in strategy guides
+static ROCK_PAPER_SCISSORS: &str = "\
+# shape <name> <score> <aliases...>
+shape Rock 1 A X
+shape Paper 2 B Y
+shape Scissors 3 C Z
+# beats <winner> <losers...>
+beats Rock Scissors
+beats Paper Rock
+beats Scissors Paper
+# outcome loss|draw|win <score> <aliases...>
+outcome loss 0 X
+outcome draw 3 Y
+outcome win 6 Z
+";
 
-static REQUIRED_OUTCOME: phf::Map<&'static str, RoundOutcome> = phf_map! {
-    "X" => RoundOutcome::Loss,
-    "Y" => RoundOutcome::Draw,
-    "Z" => RoundOutcome::Win,
-};
+#[derive(Debug)]
+struct Game {
+    shapes: Vec<String>,
+    // outcome and points for you of every pair of shapes, at them * shapes.len() + you
+    outcomes: Vec<Outcome>,
+    points: Vec<i32>,
+    // shape names and their aliases
+    symbols: HashMap<String, Shape>,
+    outcome_symbols: HashMap<String, Outcome>,
+}
 
-fn round_outcome(them: &str, you: &str) -> RoundOutcome {
-    if them == you {
-        return RoundOutcome::Draw;
+impl Game {
+    fn rock_paper_scissors() -> Game {
+        Game::parse(ROCK_PAPER_SCISSORS).unwrap()
     }
 
-    if *DEFEATS.get(you).unwrap() == them {
-        return RoundOutcome::Win;
+    fn parse(config: &str) -> Result<Game, Vec<Problem>> {
+        let mut game = Game {
+            shapes: vec![],
+            outcomes: vec![],
+            points: vec![],
+            symbols: HashMap::new(),
+            outcome_symbols: HashMap::new(),
+        };
+        let mut scores: Vec<i32> = vec![];
+        let mut outcome_scores = [0; 3];
+        let mut beats = vec![];
+        let mut outcomes_seen = [false; 3];
+        let mut problems = vec![];
+        for (i, line) in config.lines().enumerate() {
+            let mut problem = |message: String| problems.push(Problem::new(i + 1, message));
+            let words: Vec<_> = line.split('#').next().unwrap().split_whitespace().collect();
+            match words.as_slice() {
+                [] => {}
+                ["shape", name, score, aliases @ ..] => {
+                    let Ok(score) = score.parse() else {
+                        problem(format!("invalid score {}", score));
+                        continue;
+                    };
+                    let shape = Shape(game.shapes.len());
+                    game.shapes.push(name.to_string());
+                    scores.push(score);
+                    for symbol in [name].into_iter().chain(aliases) {
+                        if game.symbols.insert(symbol.to_string(), shape).is_some() {
+                            problem(format!("{} names more than one shape", symbol));
+                        }
+                    }
+                }
+                ["beats", winner, losers @ ..] if !losers.is_empty() => {
+                    for loser in losers {
+                        beats.push((i + 1, winner.to_string(), loser.to_string()));
+                    }
+                }
+                ["outcome", outcome, score, aliases @ ..] => {
+                    let outcome = match *outcome {
+                        "loss" => Outcome::Loss,
+                        "draw" => Outcome::Draw,
+                        "win" => Outcome::Win,
+                        _ => {
+                            problem(format!("unknown outcome {}", outcome));
+                            continue;
+                        }
+                    };
+                    match score.parse() {
+                        Ok(score) => outcome_scores[outcome as usize] = score,
+                        Err(_) => problem(format!("invalid score {}", score)),
+                    }
+                    outcomes_seen[outcome as usize] = true;
+                    for symbol in aliases {
+                        if game
+                            .outcome_symbols
+                            .insert(symbol.to_string(), outcome)
+                            .is_some()
+                        {
+                            problem(format!("{} names more than one outcome", symbol));
+                        }
+                    }
+                }
+                _ => problem(format!("cannot understand {}", line.trim())),
+            }
+        }
+
+        let n = game.shapes.len();
+        // beats[a][b] is true when shape a defeats shape b, neither beating the other is a draw
+        let mut beats_table = vec![vec![false; n]; n];
+        for (line, winner, loser) in beats {
+            let mut problem = |message: String| problems.push(Problem::new(line, message));
+            match (game.symbols.get(&winner), game.symbols.get(&loser)) {
+                (Some(&w), Some(&l)) if w == l => problem(format!("{} cannot beat itself", winner)),
+                (Some(&Shape(w)), Some(&Shape(l))) if beats_table[l][w] => {
+                    problem(format!("{} and {} beat each other", winner, loser))
+                }
+                (Some(&Shape(w)), Some(&Shape(l))) => beats_table[w][l] = true,
+                (None, _) => problem(format!("unknown shape {}", winner)),
+                (_, None) => problem(format!("unknown shape {}", loser)),
+            }
+        }
+        let last_line = config.lines().count().max(1);
+        if n == 0 {
+            problems.push(Problem::new(last_line, "no shapes defined"));
+        }
+        for (outcome, seen) in ["loss", "draw", "win"].iter().zip(outcomes_seen) {
+            if !seen {
+                problems.push(Problem::new(
+                    last_line,
+                    format!("no score for a {}", outcome),
+                ));
+            }
+        }
+
+        for (them, beaten_by_them) in beats_table.iter().enumerate() {
+            for (you, beaten_by_you) in beats_table.iter().enumerate() {
+                let outcome = if beaten_by_you[them] {
+                    Outcome::Win
+                } else if beaten_by_them[you] {
+                    Outcome::Loss
+                } else {
+                    Outcome::Draw
+                };
+                game.outcomes.push(outcome);
+                game.points
+                    .push(scores[you] + outcome_scores[outcome as usize]);
+            }
+        }
+        if problems.is_empty() {
+            Ok(game)
+        } else {
+            problems.sort_by_key(|p| p.line);
+            Err(problems)
+        }
+    }
+
+    fn shapes(&self) -> impl Iterator<Item = Shape> {
+        (0..self.shapes.len()).map(Shape)
     }
 
-    return RoundOutcome::Loss;
+    fn shape(&self, symbol: &str) -> Option<Shape> {
+        self.symbols.get(symbol).copied()
+    }
+
+    fn name(&self, shape: Shape) -> &str {
+        &self.shapes[shape.0]
+    }
+
+    fn round_outcome(&self, them: Shape, you: Shape) -> Outcome {
+        self.outcomes[them.0 * self.shapes.len() + you.0]
+    }
+
+    fn score(&self, them: Shape, you: Shape) -> i32 {
+        self.points[them.0 * self.shapes.len() + you.0]
+    }
+}
+
+struct Type1Scorer<'a> {
+    game: &'a Game,
 }
 
-struct Type1Scorer {}
-impl Type1Scorer {}
-impl RoundScorer for Type1Scorer {
-    fn score_round(them: &str, you: &str) -> i32 {
-        let (them, you) = translate(them, you);
-        let shape_score = *SHAPE_SCORES.get(&you).unwrap();
-        let round_score = round_outcome(&them, &you) as i32;
-        shape_score + round_score
+impl RoundScorer for Type1Scorer<'_> {
+    type Column = Shape;
+
+    fn game(&self) -> &Game {
+        self.game
+    }
+
+    fn column(&self, symbol: &str) -> Result<Shape, String> {
+        self.game
+            .shape(symbol)
+            .ok_or_else(|| format!("unknown shape {}", symbol))
     }
+
+    fn play(&self, _: Shape, you: Shape) -> Shape {
+        you
+    }
+}
+
+struct Type2Scorer<'a> {
+    game: &'a Game,
+    // required_plays[them][outcome] is the only shape giving that outcome
+    required_plays: Vec<[Shape; 3]>,
 }
 
-struct Type2Scorer {}
-impl Type2Scorer {
-    fn required_play(them: &str, outcome: RoundOutcome) -> String {
-        match outcome {
-            RoundOutcome::Loss => (*DEFEATS.get(them).unwrap()).into(),
-            RoundOutcome::Draw => them.into(),
-            RoundOutcome::Win => (*DEFEATS
-                .into_iter()
-                .find(|(_, b)| **b == them)
-                .map(|(a, _)| a)
-                .unwrap())
-            .into(),
+impl<'a> Type2Scorer<'a> {
+    // the second column only makes sense if every outcome has exactly one way to get it
+    fn new(game: &'a Game) -> Result<Self, String> {
+        let mut required_plays = vec![[Shape(0); 3]; game.shapes.len()];
+        for (them, plays_against) in game.shapes().zip(required_plays.iter_mut()) {
+            for outcome in Outcome::ALL {
+                let plays: Vec<_> = game
+                    .shapes()
+                    .filter(|&you| game.round_outcome(them, you) == outcome)
+                    .collect();
+                match plays.as_slice() {
+                    [play] => plays_against[outcome as usize] = *play,
+                    _ => {
+                        return Err(format!(
+                            "{} ways to get a {:?} against {}",
+                            plays.len(),
+                            outcome,
+                            game.name(them)
+                        ))
+                    }
+                }
+            }
         }
+        Ok(Self {
+            game,
+            required_plays,
+        })
+    }
+
+    fn required_play(&self, them: Shape, outcome: Outcome) -> Shape {
+        self.required_plays[them.0][outcome as usize]
     }
 }
-impl RoundScorer for Type2Scorer {
-    fn score_round(them: &str, you: &str) -> i32 {
-        let (them, _) = translate(them, you); // only translate them at this point
-        let your_play = Self::required_play(&them, *REQUIRED_OUTCOME.get(you).unwrap());
-        let shape_score = *SHAPE_SCORES.get(&your_play).unwrap();
-        let round_score = round_outcome(&them, &your_play) as i32;
-        shape_score + round_score
+
+impl RoundScorer for Type2Scorer<'_> {
+    type Column = Outcome;
+
+    fn game(&self) -> &Game {
+        self.game
+    }
+
+    fn column(&self, symbol: &str) -> Result<Outcome, String> {
+        self.game
+            .outcome_symbols
+            .get(symbol)
+            .copied()
+            .ok_or_else(|| format!("unknown outcome {}", symbol))
+    }
+
+    fn play(&self, them: Shape, you: Outcome) -> Shape {
+        self.required_play(them, you)
     }
 }
 
 trait RoundScorer {
-    fn score_round(them: &str, you: &str) -> i32;
+    // what the second column of a guide means to this scorer
+    type Column: Copy;
+
+    fn game(&self) -> &Game;
+
+    fn column(&self, symbol: &str) -> Result<Self::Column, String>;
+
+    // the shape to throw against `them`
+    fn play(&self, them: Shape, you: Self::Column) -> Shape;
+
+    fn score_round(&self, them: Shape, you: Self::Column) -> i32 {
+        self.game().score(them, self.play(them, you))
+    }
+}
+
+// how a guide plays out under one scorer
+#[derive(Debug, PartialEq)]
+struct Analysis {
+    shapes: Vec<String>,
+    // indexed by Outcome
+    outcomes: [usize; 3],
+    // rounds played and points scored with each shape
+    by_shape: Vec<(usize, i32)>,
+}
+
+impl Display for Analysis {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [losses, draws, wins] = self.outcomes;
+        writeln!(f, "{} wins, {} draws, {} losses", wins, draws, losses)?;
+        for (shape, (rounds, points)) in self.shapes.iter().zip(&self.by_shape) {
+            writeln!(f, "{}: {} rounds, {} points", shape, rounds, points)?;
+        }
+        let total: i32 = self.by_shape.iter().map(|(_, points)| points).sum();
+        write!(f, "total: {} points", total)
+    }
+}
+
+fn analyse<S: RoundScorer>(scorer: &S, input: &str) -> Result<Analysis, Problem> {
+    let game = scorer.game();
+    let mut analysis = Analysis {
+        shapes: game.shapes.clone(),
+        outcomes: [0; 3],
+        by_shape: vec![(0, 0); game.shapes.len()],
+    };
+    for round in rounds(game, input) {
+        let (line, them, you) = round?;
+        let you = scorer
+            .column(you)
+            .map_err(|message| Problem::new(line, message))?;
+        let you = scorer.play(them, you);
+        analysis.outcomes[game.round_outcome(them, you) as usize] += 1;
+        analysis.by_shape[you.0].0 += 1;
+        analysis.by_shape[you.0].1 += game.score(them, you);
+    }
+    Ok(analysis)
+}
+
+// the score of always throwing the best shape
+fn optimal_score(game: &Game, input: &str) -> Result<i32, Problem> {
+    let mut total = 0;
+    for round in rounds(game, input) {
+        let (_, them, _) = round?;
+        total += game
+            .shapes()
+            .map(|you| game.score(them, you))
+            .max()
+            .unwrap();
+    }
+    Ok(total)
+}
+
+// scores every way of reading the second column as distinct shapes, and as required outcomes
+// when the game allows it, best first
+fn reinterpretations(game: &Game, input: &str) -> Result<Vec<(String, i32)>, Problem> {
+    let guide = rounds(game, input).collect::<Result<Vec<_>, _>>()?;
+    let mut symbols: Vec<&str> = guide.iter().map(|&(_, _, you)| you).collect();
+    symbols.sort();
+    symbols.dedup();
+    // rounds per opponent shape and second column symbol, so each reading costs no more than this
+    let mut counts: HashMap<(Shape, usize), i32> = HashMap::new();
+    for (_, them, you) in guide {
+        let symbol = symbols.binary_search(&you).unwrap();
+        *counts.entry((them, symbol)).or_default() += 1;
+    }
+
+    let mut readings = vec![];
+    let mut mapping = vec![];
+    let mut used = vec![false; game.shapes.len()];
+    each_mapping(symbols.len(), &mut mapping, &mut used, &mut |mapping| {
+        let score = counts
+            .iter()
+            .map(|(&(them, symbol), &n)| n * game.score(them, Shape(mapping[symbol])))
+            .sum();
+        let reading: Vec<_> = symbols
+            .iter()
+            .zip(mapping)
+            .map(|(symbol, &shape)| format!("{}={}", symbol, game.name(Shape(shape))))
+            .collect();
+        readings.push((reading.join(" "), score));
+    });
+    if let Ok(scorer) = Type2Scorer::new(game) {
+        let outcomes: Option<Vec<_>> = symbols
+            .iter()
+            .map(|symbol| game.outcome_symbols.get(*symbol))
+            .collect();
+        if let Some(outcomes) = outcomes {
+            let score = counts
+                .iter()
+                .map(|(&(them, symbol), &n)| {
+                    n * game.score(them, scorer.required_play(them, *outcomes[symbol]))
+                })
+                .sum();
+            let reading: Vec<_> = symbols
+                .iter()
+                .zip(&outcomes)
+                .map(|(symbol, outcome)| format!("{}={:?}", symbol, outcome))
+                .collect();
+            readings.push((reading.join(" "), score));
+        }
+    }
+    readings.sort_by_key(|(_, score)| -score);
+    Ok(readings)
 }
 
-fn translate(them: &str, you: &str) -> (String, String) {
-    (
-        (*PRETTY_SHAPE.get(them).unwrap_or(&them)).into(),
-        (*PRETTY_SHAPE.get(you).unwrap_or(&you)).into(),
-    )
+// calls `f` with every assignment of distinct shape indices to `n` symbols
+fn each_mapping(
+    n: usize,
+    mapping: &mut Vec<usize>,
+    used: &mut Vec<bool>,
+    f: &mut impl FnMut(&[usize]),
+) {
+    if mapping.len() == n {
+        f(mapping);
+        return;
+    }
+    for shape in 0..used.len() {
+        if !used[shape] {
+            used[shape] = true;
+            mapping.push(shape);
+            each_mapping(n, mapping, used, f);
+            mapping.pop();
+            used[shape] = false;
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{
-        days::day2::{RoundOutcome, RoundScorer, Type1Scorer, Type2Scorer},
-        solution::Solution,
-    };
+    use crate::solution::Solution;
 
-    use super::{get_input, round_outcome, solve1, solve2};
+    use super::{
+        analyse, get_input, optimal_score, reinterpretations, solve1, solve2, total_score, Game,
+        Outcome, Problem, Type1Scorer, Type2Scorer,
+    };
 
     fn sample_input() -> String {
         get_input(2, true, None)
     }
 
+    fn round_outcome(game: &Game, them: &str, you: &str) -> Outcome {
+        game.round_outcome(game.shape(them).unwrap(), game.shape(you).unwrap())
+    }
+
     #[test]
     fn round_outcome_test() {
+        let game = Game::rock_paper_scissors();
         // tie
-        assert_eq!(RoundOutcome::Draw, round_outcome("Rock", "Rock"));
-        assert_eq!(RoundOutcome::Draw, round_outcome("Paper", "Paper"));
-        assert_eq!(RoundOutcome::Draw, round_outcome("Scissors", "Scissors"));
+        assert_eq!(Outcome::Draw, round_outcome(&game, "Rock", "Rock"));
+        assert_eq!(Outcome::Draw, round_outcome(&game, "Paper", "Paper"));
+        assert_eq!(Outcome::Draw, round_outcome(&game, "Scissors", "Scissors"));
         // they win
-        assert_eq!(RoundOutcome::Loss, round_outcome("Rock", "Scissors"));
-        assert_eq!(RoundOutcome::Loss, round_outcome("Paper", "Rock"));
-        assert_eq!(RoundOutcome::Loss, round_outcome("Scissors", "Paper"));
+        assert_eq!(Outcome::Loss, round_outcome(&game, "Rock", "Scissors"));
+        assert_eq!(Outcome::Loss, round_outcome(&game, "Paper", "Rock"));
+        assert_eq!(Outcome::Loss, round_outcome(&game, "Scissors", "Paper"));
         // we win
-        assert_eq!(RoundOutcome::Win, round_outcome("Scissors", "Rock"));
-        assert_eq!(RoundOutcome::Win, round_outcome("Rock", "Paper"));
-        assert_eq!(RoundOutcome::Win, round_outcome("Paper", "Scissors"));
+        assert_eq!(Outcome::Win, round_outcome(&game, "Scissors", "Rock"));
+        assert_eq!(Outcome::Win, round_outcome(&game, "Rock", "Paper"));
+        assert_eq!(Outcome::Win, round_outcome(&game, "Paper", "Scissors"));
     }
 
     #[test]
     fn round_score_test() {
-        assert_eq!(8, Type1Scorer::score_round("A", "Y"));
-        assert_eq!(1, Type1Scorer::score_round("B", "X"));
-        assert_eq!(6, Type1Scorer::score_round("Scissors", "Scissors"));
+        let game = Game::rock_paper_scissors();
+        let scorer = Type1Scorer { game: &game };
+        assert_eq!(Ok(8), total_score(&scorer, "A Y"));
+        assert_eq!(Ok(1), total_score(&scorer, "B X"));
+        assert_eq!(Ok(6), total_score(&scorer, "Scissors Scissors"));
     }
 
     #[test]
@@ -176,13 +566,107 @@ mod test {
 
     #[test]
     fn round_score_test_2() {
-        assert_eq!(4, Type2Scorer::score_round("A", "Y"));
-        assert_eq!(1, Type2Scorer::score_round("B", "X"));
-        assert_eq!(7, Type2Scorer::score_round("Scissors", "Z"));
+        let game = Game::rock_paper_scissors();
+        let scorer = Type2Scorer::new(&game).unwrap();
+        assert_eq!(Ok(4), total_score(&scorer, "A Y"));
+        assert_eq!(Ok(1), total_score(&scorer, "B X"));
+        assert_eq!(Ok(7), total_score(&scorer, "Scissors Z"));
     }
 
     #[test]
     fn test2() {
         assert_eq!(Solution::I32(12), solve2(&sample_input()));
     }
+
+    #[test]
+    fn guide_errors() {
+        let game = Game::rock_paper_scissors();
+        let type1 = Type1Scorer { game: &game };
+        let type2 = Type2Scorer::new(&game).unwrap();
+        assert_eq!(
+            Err(Problem::new(3, "unknown shape Q")),
+            total_score(&type1, "A Y\n\nB Q")
+        );
+        assert_eq!(
+            Err(Problem::new(2, "unknown outcome Rock")),
+            total_score(&type2, "A Y\nB Rock")
+        );
+        assert_eq!(
+            Err(Problem::new(1, "unknown shape D")),
+            total_score(&type2, "D Y")
+        );
+        assert_eq!(
+            Err(Problem::new(2, "expected two symbols, got A X Y")),
+            optimal_score(&game, "A Y\nA X Y")
+        );
+    }
+
+    #[test]
+    fn many_to_many() {
+        let game = Game::parse(
+            "shape Rock 1 R\nshape Paper 2 P\nshape Scissors 3 S\nshape Lizard 4 L\nshape Spock 5 K
+beats Rock Scissors Lizard\nbeats Paper Rock Spock\nbeats Scissors Paper Lizard
+beats Lizard Paper Spock\nbeats Spock Rock Scissors
+outcome loss 0\noutcome draw 3\noutcome win 6",
+        )
+        .unwrap();
+        let scorer = Type1Scorer { game: &game };
+        assert_eq!(Ok(11), total_score(&scorer, "Rock Spock"));
+        assert_eq!(Ok(10), total_score(&scorer, "K L"));
+        assert_eq!(Ok(1), total_score(&scorer, "P R"));
+        assert_eq!(
+            "2 ways to get a Loss against Rock",
+            Type2Scorer::new(&game).err().unwrap()
+        );
+    }
+
+    #[test]
+    fn invalid_rules() {
+        assert_eq!(
+            vec![
+                Problem::new(2, "A names more than one shape"),
+                Problem::new(3, "invalid score x"),
+                Problem::new(4, "Rock cannot beat itself"),
+                Problem::new(5, "unknown shape Stone"),
+                Problem::new(7, "Paper and Rock beat each other"),
+                Problem::new(8, "unknown outcome tie"),
+                Problem::new(11, "cannot understand stalemate 3"),
+                Problem::new(11, "no score for a draw"),
+            ],
+            Game::parse(
+                "shape Rock 1 A\nshape Paper 2 A\nshape Scissors x\nbeats Rock Rock
+beats Stone Rock\nbeats Rock Paper\nbeats Paper Rock\noutcome tie 3\noutcome loss 0
+outcome win 6\nstalemate 3"
+            )
+            .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn analysis() {
+        let game = Game::rock_paper_scissors();
+        let part1 = analyse(&Type1Scorer { game: &game }, &sample_input()).unwrap();
+        assert_eq!([1, 1, 1], part1.outcomes);
+        assert_eq!(vec![(1, 1), (1, 8), (1, 6)], part1.by_shape);
+        let part2 = analyse(&Type2Scorer::new(&game).unwrap(), &sample_input()).unwrap();
+        assert_eq!([1, 1, 1], part2.outcomes);
+        assert_eq!(vec![(3, 12), (0, 0), (0, 0)], part2.by_shape);
+        assert_eq!(
+            "1 wins, 1 draws, 1 losses\nRock: 3 rounds, 12 points\nPaper: 0 rounds, 0 points
+Scissors: 0 rounds, 0 points\ntotal: 12 points",
+            part2.to_string()
+        );
+    }
+
+    #[test]
+    fn optimiser() {
+        let game = Game::rock_paper_scissors();
+        // paper beats A, scissors beats B, rock beats C
+        assert_eq!(Ok(8 + 9 + 7), optimal_score(&game, &sample_input()));
+        let readings = reinterpretations(&game, &sample_input()).unwrap();
+        assert_eq!(7, readings.len());
+        assert_eq!(("X=Scissors Y=Paper Z=Rock".to_string(), 24), readings[0]);
+        assert!(readings.contains(&("X=Rock Y=Paper Z=Scissors".to_string(), 15)));
+        assert!(readings.contains(&("X=Loss Y=Draw Z=Win".to_string(), 12)));
+    }
 }
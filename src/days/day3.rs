@@ -1,7 +1,9 @@
-use std::collections::HashSet;
+use std::fmt::{self, Display};
 
 use crate::{
+    cli::Args,
     input::get_input,
+    problem::Problem,
     solution::{Solution, SolutionPair},
 };
 
@@ -11,102 +13,307 @@ pub fn solve() -> SolutionPair {
 }
 
 fn solve1(input: &str) -> Solution {
-    let val = input
-        .split("\n")
-        .collect::<Vec<_>>()
-        .iter()
-        .map(|line| {
-            let (left, right) = split_sack(line);
-            let common = find_common_item(&left, &right).unwrap();
-            item_priority(&common)
-        })
-        .sum();
-    Solution::I32(val)
+    let sacks = read_sacks(input);
+    refuse(diagnose_sacks(&sacks));
+    Solution::I32(compartment_priorities(&sacks))
 }
 
 fn solve2(input: &str) -> Solution {
-    let val = groups_of_3(input)
+    let sacks = read_sacks(input);
+    refuse(diagnose_groups(&sacks, 3));
+    Solution::I32(badge_priorities(&sacks, 3))
+}
+
+fn read_sacks(input: &str) -> Vec<Sack> {
+    parse_sacks(input).unwrap_or_else(|e| panic!("{}", e))
+}
+
+// an answer only means something when none of the sacks or groups it adds up is in doubt
+fn refuse(problems: Vec<Problem>) {
+    if !problems.is_empty() {
+        let report: Vec<_> = problems.iter().map(|p| p.to_string()).collect();
+        panic!("invalid sacks:\n{}", report.join("\n"));
+    }
+}
+
+pub fn tool(args: &Args) {
+    let sacks = read_sacks(&args.input(3));
+    let group_size = args.value("group-size").unwrap_or(3);
+    if group_size == 0 {
+        println!("a group needs at least one sack");
+        return;
+    }
+    let sack_problems = diagnose_sacks(&sacks);
+    let group_problems = diagnose_groups(&sacks, group_size);
+    for problem in diagnose(&sacks, group_size) {
+        println!("{}", problem);
+    }
+    if args.flag("diagnostics") {
+        println!("item priority shared badge sacks");
+        for stats in priority_stats(&sacks, group_size) {
+            println!(
+                "{:>4} {:>8} {:>6} {:>5} {:>5}",
+                item_name(stats.priority),
+                stats.priority,
+                stats.shared,
+                stats.badges,
+                stats.sacks
+            );
+        }
+    }
+    if sack_problems.is_empty() {
+        println!("part 1: {}", compartment_priorities(&sacks));
+    } else {
+        println!(
+            "part 1: no total, {} problems in the sacks",
+            sack_problems.len()
+        );
+    }
+    if group_problems.is_empty() {
+        println!("part 2: {}", badge_priorities(&sacks, group_size));
+    } else {
+        println!(
+            "part 2: no total, {} problems in the groups",
+            group_problems.len()
+        );
+    }
+}
+
+// sacks without exactly one shared item are left out, `diagnose` reports them
+fn compartment_priorities(sacks: &[Sack]) -> i32 {
+    sacks
         .iter()
-        .map(|(one, two, three)| {
-            let common1and2 = find_common_items(one, two);
-            let set3: HashSet<_> = three.split("").into_iter().map(|i| i.to_string()).collect();
-            let intersection = common1and2.intersection(&set3).collect::<Vec<_>>();
-            let common = intersection.first().unwrap();
-            item_priority(common)
+        .filter_map(|sack| sack.left.intersection(sack.right).single())
+        .sum()
+}
+
+// groups without exactly one badge are left out, `diagnose` reports them
+fn badge_priorities(sacks: &[Sack], group_size: usize) -> i32 {
+    groups(sacks, group_size)
+        .filter_map(|group| ItemSet::intersect_all(group.iter().map(Sack::items)).single())
+        .sum()
+}
+
+// items a-z have priorities 1-26 and A-Z 27-52, an item is in the set when bit priority - 1 is
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+struct ItemSet(u64);
+
+impl ItemSet {
+    const ALL: ItemSet = ItemSet((1 << 52) - 1);
+
+    // the first character that is not an item is the error
+    fn parse(items: &str) -> Result<ItemSet, char> {
+        items.chars().try_fold(ItemSet::default(), |set, item| {
+            Ok(ItemSet(set.0 | 1 << (item_priority(item)? - 1)))
         })
-        .sum();
-    Solution::I32(val)
-}
-
-static LETTERS: &'static str = "abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ";
-
-fn item_priority(item: &str) -> i32 {
-    LETTERS.find(item).unwrap() as i32 + 1
-}
-
-fn split_sack(sack: &str) -> (String, String) {
-    let items = sack.split("").into_iter().collect::<Vec<_>>();
-    (
-        items[0..items.len() / 2].join(""),
-        items[items.len() / 2..items.len()].join(""),
-    )
-}
-
-fn find_common_item(left: &str, right: &str) -> Option<String> {
-    let left = left
-        .split("")
-        .into_iter()
-        .filter(|i| !i.is_empty())
-        .collect::<HashSet<_>>();
-    let right = right
-        .split("")
-        .into_iter()
-        .filter(|i| !i.is_empty())
-        .collect::<HashSet<_>>();
-    let intersection = left.intersection(&right);
-    intersection
-        .into_iter()
-        .collect::<Vec<_>>()
-        .first()
-        .map(|i| i.to_string())
-}
-
-fn find_common_items(left: &str, right: &str) -> HashSet<String> {
-    let left = left
-        .split("")
-        .into_iter()
-        .filter(|i| !i.is_empty())
-        .collect::<HashSet<_>>();
-    let right = right
-        .split("")
-        .into_iter()
-        .filter(|i| !i.is_empty())
-        .collect::<HashSet<_>>();
-    let intersection = left.intersection(&right);
-    intersection.into_iter().map(|i| i.to_string()).collect()
-}
-
-fn groups_of_3(lines: &str) -> Vec<(String, String, String)> {
-    lines
-        .split("\n")
-        .into_iter()
-        .filter(|i| !i.is_empty())
-        .array_chunks::<3>()
-        .map(|chunk| {
-            (
-                chunk[0].to_string(),
-                chunk[1].to_string(),
-                chunk[2].to_string(),
-            )
+    }
+
+    fn union(self, other: ItemSet) -> ItemSet {
+        ItemSet(self.0 | other.0)
+    }
+
+    fn intersection(self, other: ItemSet) -> ItemSet {
+        ItemSet(self.0 & other.0)
+    }
+
+    // the items every set has in common, which is every item for no sets at all
+    fn intersect_all(sets: impl IntoIterator<Item = ItemSet>) -> ItemSet {
+        sets.into_iter().fold(ItemSet::ALL, ItemSet::intersection)
+    }
+
+    fn len(self) -> u32 {
+        self.0.count_ones()
+    }
+
+    // the priority of the only item in the set
+    fn single(self) -> Option<i32> {
+        (self.len() == 1).then(|| self.0.trailing_zeros() as i32 + 1)
+    }
+
+    // in increasing order
+    fn priorities(self) -> impl Iterator<Item = i32> {
+        (0..52)
+            .filter(move |bit| self.0 & 1 << bit != 0)
+            .map(|bit| bit + 1)
+    }
+}
+
+// the items in priority order
+impl Display for ItemSet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.priorities()
+            .try_for_each(|priority| write!(f, "{}", item_name(priority)))
+    }
+}
+
+fn item_name(priority: i32) -> char {
+    match priority {
+        1..=26 => (b'a' + priority as u8 - 1) as char,
+        27..=52 => (b'A' + priority as u8 - 27) as char,
+        _ => panic!("no item has priority {}", priority),
+    }
+}
+
+fn item_priority(item: char) -> Result<i32, char> {
+    match item {
+        'a'..='z' => Ok(item as i32 - 'a' as i32 + 1),
+        'A'..='Z' => Ok(item as i32 - 'A' as i32 + 27),
+        _ => Err(item),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Sack {
+    line: usize,
+    len: usize,
+    left: ItemSet,
+    right: ItemSet,
+}
+
+impl Sack {
+    fn items(&self) -> ItemSet {
+        self.left.union(self.right)
+    }
+}
+
+// halves by character, so a stray multibyte character cannot end up split
+fn split_sack(sack: &str) -> (&str, &str) {
+    let half = sack.chars().count() / 2;
+    sack.split_at(sack.char_indices().nth(half).map_or(sack.len(), |(i, _)| i))
+}
+
+fn parse_sacks(input: &str) -> Result<Vec<Sack>, Problem> {
+    input
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty())
+        .map(|(i, l)| {
+            let l = l.trim();
+            let problem = |item: char| Problem::new(i + 1, format!("{:?} is not an item", item));
+            let (left, right) = split_sack(l);
+            Ok(Sack {
+                line: i + 1,
+                len: l.chars().count(),
+                left: ItemSet::parse(left).map_err(problem)?,
+                right: ItemSet::parse(right).map_err(problem)?,
+            })
         })
         .collect()
 }
 
+// consecutive sacks, sacks left over after the last full group are in none
+fn groups(sacks: &[Sack], size: usize) -> impl Iterator<Item = &[Sack]> {
+    assert!(size > 0, "groups need at least one sack");
+    sacks.chunks_exact(size)
+}
+
+// the sacks and groups that break the puzzle's assumptions, in line order
+fn diagnose(sacks: &[Sack], group_size: usize) -> Vec<Problem> {
+    let mut problems = diagnose_sacks(sacks);
+    problems.extend(diagnose_groups(sacks, group_size));
+    // stable, so a sack's own problems stay ahead of its group's
+    problems.sort_by_key(|problem| problem.line);
+    problems
+}
+
+// the sacks part 1 cannot score
+fn diagnose_sacks(sacks: &[Sack]) -> Vec<Problem> {
+    let mut problems = vec![];
+    for sack in sacks {
+        let mut report = |message: String| problems.push(Problem::new(sack.line, message));
+        if sack.len % 2 != 0 {
+            report(format!(
+                "odd length {}, the second compartment gets the extra item",
+                sack.len
+            ));
+        }
+        let shared = sack.left.intersection(sack.right);
+        match shared.len() {
+            0 => report("no item is in both compartments".into()),
+            1 => {}
+            _ => report(format!("items {} are in both compartments", shared)),
+        }
+    }
+    problems
+}
+
+// the sacks part 2 cannot put in a group with a single badge
+fn diagnose_groups(sacks: &[Sack], group_size: usize) -> Vec<Problem> {
+    let mut problems = vec![];
+    let left_over = &sacks[sacks.len() - sacks.len() % group_size..];
+    if let (Some(first), Some(last)) = (left_over.first(), left_over.last()) {
+        problems.push(Problem::new(
+            first.line,
+            format!(
+                "lines {}-{} are not part of a full group of {}",
+                first.line, last.line, group_size
+            ),
+        ));
+    }
+    for group in groups(sacks, group_size) {
+        let (first, last) = (group[0].line, group[group.len() - 1].line);
+        let mut report = |message: &str| {
+            problems.push(Problem::new(
+                first,
+                format!("group of lines {}-{} {}", first, last, message),
+            ))
+        };
+        let badges = ItemSet::intersect_all(group.iter().map(Sack::items));
+        match badges.len() {
+            0 => report("has no badge"),
+            1 => {}
+            _ => report(&format!("has several possible badges {}", badges)),
+        }
+    }
+    problems.sort_by_key(|problem| problem.line);
+    problems
+}
+
+#[derive(Debug, Default, PartialEq)]
+struct PriorityStats {
+    priority: i32,
+    // sacks with the item in both compartments
+    shared: usize,
+    // groups with the item in every sack
+    badges: usize,
+    // sacks with the item anywhere
+    sacks: usize,
+}
+
+// only the items that turn up somewhere, by priority
+fn priority_stats(sacks: &[Sack], group_size: usize) -> Vec<PriorityStats> {
+    let mut stats: Vec<_> = (1..=52)
+        .map(|priority| PriorityStats {
+            priority,
+            ..Default::default()
+        })
+        .collect();
+    let index = |priority: i32| priority as usize - 1;
+    for sack in sacks {
+        for priority in sack.left.intersection(sack.right).priorities() {
+            stats[index(priority)].shared += 1;
+        }
+        for priority in sack.items().priorities() {
+            stats[index(priority)].sacks += 1;
+        }
+    }
+    for group in groups(sacks, group_size) {
+        for priority in ItemSet::intersect_all(group.iter().map(Sack::items)).priorities() {
+            stats[index(priority)].badges += 1;
+        }
+    }
+    stats.retain(|s| s.sacks > 0);
+    stats
+}
+
 #[cfg(test)]
 mod test {
     use crate::{input::get_input, solution::Solution};
 
-    use super::{find_common_item, groups_of_3, item_priority, solve1, solve2, split_sack};
+    use super::{
+        badge_priorities, compartment_priorities, diagnose, groups, item_name, item_priority,
+        parse_sacks, priority_stats, solve1, solve2, split_sack, ItemSet, PriorityStats, Problem,
+    };
 
     fn sample_input() -> String {
         get_input(3, true, None)
@@ -114,20 +321,40 @@ mod test {
 
     #[test]
     fn priority_test() {
-        assert_eq!(1, item_priority("a"));
-        assert_eq!(26, item_priority("z"));
-        assert_eq!(27, item_priority("A"));
-        assert_eq!(52, item_priority("Z"));
+        assert_eq!(Ok(1), item_priority('a'));
+        assert_eq!(Ok(26), item_priority('z'));
+        assert_eq!(Ok(27), item_priority('A'));
+        assert_eq!(Ok(52), item_priority('Z'));
+        assert_eq!(Err('1'), item_priority('1'));
+        for item in ['a', 'q', 'A', 'Z'] {
+            assert_eq!(item, item_name(item_priority(item).unwrap()));
+        }
     }
 
     #[test]
     fn split_test() {
-        assert_eq!(("123".to_string(), "456".to_string()), split_sack("123456"));
+        assert_eq!(("123", "456"), split_sack("123456"));
+        assert_eq!(("aé", "bcd"), split_sack("aébcd"));
     }
 
     #[test]
     fn common_test() {
-        assert_eq!("a", find_common_item("1a43", "bdaf").unwrap());
+        assert_eq!(Err('4'), ItemSet::parse("Xa4f"));
+        let (left, right) = (
+            ItemSet::parse("Xaef").unwrap(),
+            ItemSet::parse("bdaX").unwrap(),
+        );
+        assert_eq!(
+            vec![1, 50],
+            left.intersection(right).priorities().collect::<Vec<_>>()
+        );
+        assert_eq!(
+            vec![1],
+            ItemSet::intersect_all([left, right, ItemSet::parse("zza").unwrap()])
+                .priorities()
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(ItemSet::ALL, ItemSet::intersect_all([]));
     }
 
     #[test]
@@ -137,30 +364,99 @@ mod test {
 
     #[test]
     fn grouping_test() {
-        assert_eq!(
-            vec![
-                ("1".to_string(), "2".to_string(), "3".to_string()),
-                ("4".to_string(), "5".to_string(), "6".to_string()),
-                ("7".to_string(), "8".to_string(), "9".to_string()),
-            ],
-            groups_of_3(
-                "
-1
-2
-3
-4
-5
-6
-7
-8
-9
-            "
-            )
-        )
+        let sacks = parse_sacks("\nab\ncd\nef\ngh\nij\nkl\nmn\n").unwrap();
+        let lines = |size| {
+            groups(&sacks, size)
+                .map(|g| g.iter().map(|s| s.line).collect::<Vec<_>>())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(vec![vec![2, 3, 4], vec![5, 6, 7]], lines(3));
+        assert_eq!(vec![vec![2, 3, 4, 5]], lines(4));
+    }
+
+    #[test]
+    fn ambiguous_sacks_are_left_out() {
+        // line 2 shares a and b, line 3 shares nothing and the group has a, b and c in common
+        let sacks = parse_sacks("cXcY\nabcab\nabcd\nabc").unwrap();
+        assert_eq!(3, compartment_priorities(&sacks));
+        assert_eq!(0, badge_priorities(&sacks[1..], 3));
+        assert_eq!(3, badge_priorities(&sacks, 3));
     }
 
     #[test]
     fn sample_2() {
         assert_eq!(Solution::I32(70), solve2(&sample_input()));
     }
+
+    #[test]
+    fn invalid_items() {
+        assert_eq!(
+            Err(Problem::new(2, "'é' is not an item")),
+            parse_sacks("abab\naébc")
+        );
+        assert_eq!(
+            Err(Problem::new(1, "'é' is not an item")),
+            parse_sacks("abcé")
+        );
+    }
+
+    #[test]
+    fn sample_is_clean() {
+        let sacks = parse_sacks(&sample_input()).unwrap();
+        assert_eq!(Vec::<Problem>::new(), diagnose(&sacks, 3));
+    }
+
+    #[test]
+    #[should_panic(expected = "line 2: no item is in both compartments")]
+    fn solve_refuses_bad_sacks() {
+        solve1(&sample_input().replacen("jqHRNqRjqzjGDLGL", "jqHRNqRjqzjGDxGx", 1));
+    }
+
+    #[test]
+    #[should_panic(expected = "line 7: lines 7-7 are not part of a full group of 3")]
+    fn solve_refuses_bad_groups() {
+        solve2(&(sample_input().trim_end().to_string() + "\nabca\n"));
+    }
+
+    #[test]
+    fn each_part_checks_only_what_it_scores() {
+        let bad_sack = sample_input().replacen("jqHRNqRjqzjGDLGL", "jqHRNqRjqzjGDxGx", 1);
+        assert_eq!(Solution::I32(70), solve2(&bad_sack));
+        let left_over = sample_input().trim_end().to_string() + "\nabca\n";
+        assert_eq!(Solution::I32(158), solve1(&left_over));
+    }
+
+    #[test]
+    fn diagnostics() {
+        let sacks = parse_sacks("abcab\nabcd\nabab\nxyzb\nbyxy").unwrap();
+        assert_eq!(
+            vec![
+                Problem::new(
+                    1,
+                    "odd length 5, the second compartment gets the extra item"
+                ),
+                Problem::new(1, "items ab are in both compartments"),
+                Problem::new(1, "group of lines 1-3 has several possible badges ab"),
+                Problem::new(2, "no item is in both compartments"),
+                Problem::new(3, "items ab are in both compartments"),
+                Problem::new(4, "no item is in both compartments"),
+                Problem::new(4, "lines 4-5 are not part of a full group of 3"),
+            ],
+            diagnose(&sacks, 3)
+        );
+        let stats = priority_stats(&sacks, 3);
+        assert_eq!(
+            Some(&PriorityStats {
+                priority: 2,
+                shared: 2,
+                badges: 1,
+                sacks: 5
+            }),
+            stats.get(1)
+        );
+        assert_eq!(
+            vec![1, 2, 3, 4, 24, 25, 26],
+            stats.iter().map(|s| s.priority).collect::<Vec<_>>()
+        );
+    }
 }
@@ -1,7 +1,7 @@
-use std::ops::RangeInclusive;
-
 use crate::{
+    cli::Args,
     input::get_input,
+    interval::{coverage, Interval, IntervalSet},
     solution::{Solution, SolutionPair},
 };
 
@@ -13,84 +13,195 @@ pub fn solve() -> SolutionPair {
 }
 
 fn solve1(input: &str) -> Solution {
-    Solution::I32(find_fully_contained(parse_pairs(input)).len() as i32)
+    Solution::I32(find_fully_contained(&parse_groups(input)).len() as i32)
 }
 
 fn solve2(input: &str) -> Solution {
-    Solution::I32(find_partly_contained(parse_pairs(input)).len() as i32)
+    Solution::I32(find_partly_contained(&parse_groups(input)).len() as i32)
+}
+
+pub fn tool(args: &Args) {
+    let groups = parse_groups(&args.input(DAY));
+    let print_lines = |query: &str, lines: Vec<usize>| {
+        let lines: Vec<_> = lines.iter().map(|l| l.to_string()).collect();
+        println!("{} ({}): {}", query, lines.len(), lines.join(" "));
+    };
+    if args.flag("queries") {
+        print_lines("one contains all", find_fully_contained(&groups));
+        print_lines("two overlap", find_partly_contained(&groups));
+        print_lines("common point", find_common_point(&groups));
+        let (depth, lines) = max_depth(&groups);
+        print_lines(&format!("max depth {}", depth), lines);
+    }
+
+    let all: Vec<_> = groups
+        .iter()
+        .flat_map(|g| g.ranges.iter().copied())
+        .collect();
+    let assigned = groups.iter().fold(IntervalSet::default(), |assigned, g| {
+        assigned.union(&IntervalSet::from_intervals(g.ranges.iter().copied()))
+    });
+    let Some(within) = args.value::<Interval>("within").or_else(|| {
+        let sections = assigned.intervals();
+        Some(Interval::new(sections.first()?.start, sections.last()?.end))
+    }) else {
+        return;
+    };
+    let runs = coverage(&all);
+    if args.flag("coverage") {
+        for (run, depth) in &runs {
+            if let Some(run) = run.intersection(&within) {
+                println!("{}\t{}", run, depth);
+            }
+        }
+    }
+    if args.flag("assigned") {
+        print_sections(&assigned.intersection(&within.into()));
+    }
+    // sections more than one assignment covers
+    if args.flag("shared") {
+        let shared = IntervalSet::from_intervals(
+            runs.iter()
+                .filter(|&&(_, depth)| depth > 1)
+                .map(|&(run, _)| run),
+        );
+        print_sections(&shared.intersection(&within.into()));
+    }
+    if args.flag("uncovered") {
+        print_sections(&IntervalSet::from(within).difference(&assigned));
+    }
+}
+
+fn print_sections(sections: &IntervalSet) {
+    for interval in sections.intervals() {
+        println!("{}", interval);
+    }
+    println!("{} sections", sections.len());
 }
 
-type Pairs = Vec<(RangeInclusive<i32>, RangeInclusive<i32>)>;
+// the comma separated assignments on one line of the input
+#[derive(Debug, PartialEq)]
+struct Group {
+    line: usize,
+    ranges: Vec<Interval>,
+}
 
-fn parse_pairs(input: &str) -> Pairs {
+fn parse_groups(input: &str) -> Vec<Group> {
     input
-        .split("\n")
-        .into_iter()
-        .filter(|l| !l.is_empty())
-        .map(|line| {
-            let ranges = line
-                .split(",")
-                .into_iter()
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty())
+        .map(|(i, line)| Group {
+            line: i + 1,
+            ranges: line
+                .split(',')
                 .map(|range| {
-                    let start_end: Vec<_> = range
-                        .split("-")
-                        .map(|n| n.parse::<i32>().unwrap())
-                        .collect();
-                    start_end[0]..=start_end[1]
+                    range
+                        .parse::<Interval>()
+                        .unwrap_or_else(|e| panic!("line {}: {}", i + 1, e))
                 })
-                .collect::<Vec<_>>();
-            (ranges[0].clone(), ranges[1].clone())
+                .collect(),
         })
         .collect()
 }
 
-fn find_fully_contained(pairs: Pairs) -> Pairs {
-    pairs
+// lines where one range contains all the others
+fn find_fully_contained(groups: &[Group]) -> Vec<usize> {
+    lines_where(groups, |ranges| {
+        let start = ranges.iter().map(|r| r.start).min().unwrap();
+        let end = ranges.iter().map(|r| r.end).max().unwrap();
+        ranges
+            .iter()
+            .any(|r| r.contains(&Interval::new(start, end)))
+    })
+}
+
+// lines where at least two ranges overlap
+fn find_partly_contained(groups: &[Group]) -> Vec<usize> {
+    lines_where(groups, |ranges| {
+        let mut sorted = ranges.to_vec();
+        sorted.sort();
+        // sorted by start, anything overlapping a range also overlaps the one right after it
+        sorted.windows(2).any(|w| w[0].overlaps(&w[1]))
+    })
+}
+
+// lines where every range shares at least one section
+fn find_common_point(groups: &[Group]) -> Vec<usize> {
+    lines_where(groups, |ranges| {
+        let start = ranges.iter().map(|r| r.start).max();
+        let end = ranges.iter().map(|r| r.end).min();
+        start <= end
+    })
+}
+
+// the most ranges of one line covering the same section, and the lines reaching it
+fn max_depth(groups: &[Group]) -> (usize, Vec<usize>) {
+    let depths: Vec<_> = groups
         .iter()
-        .filter(|(left, right)| {
-            left.clone().into_iter().all(|l| right.contains(&l))
-                || right.clone().into_iter().all(|r| left.contains(&r))
+        .map(|g| {
+            let depth = coverage(&g.ranges).iter().map(|&(_, d)| d).max();
+            (depth.unwrap_or(0), g.line)
         })
-        .cloned()
-        .collect()
+        .collect();
+    let max = depths.iter().map(|&(d, _)| d).max().unwrap_or(0);
+    let lines = depths
+        .iter()
+        .filter(|&&(d, _)| d == max)
+        .map(|&(_, line)| line)
+        .collect();
+    (max, lines)
 }
 
-fn find_partly_contained(pairs: Pairs) -> Pairs {
-    pairs
+fn lines_where(groups: &[Group], predicate: impl Fn(&[Interval]) -> bool) -> Vec<usize> {
+    groups
         .iter()
-        .filter(|(left, right)| left.clone().into_iter().any(|l| right.contains(&l)))
-        .cloned()
+        .filter(|g| predicate(&g.ranges))
+        .map(|g| g.line)
         .collect()
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{input::get_input, solution::Solution};
+    use crate::{input::get_input, interval::Interval, solution::Solution};
 
-    use super::{find_fully_contained, find_partly_contained, parse_pairs, solve1, solve2, DAY};
+    use super::{
+        find_common_point, find_fully_contained, find_partly_contained, max_depth, parse_groups,
+        solve1, solve2, Group, DAY,
+    };
 
     fn sample_input() -> String {
         get_input(DAY, true, None)
     }
 
+    fn group(line: usize, ranges: &[(i64, i64)]) -> Group {
+        Group {
+            line,
+            ranges: ranges.iter().map(|&(s, e)| Interval::new(s, e)).collect(),
+        }
+    }
+
     #[test]
     fn parse_test() {
-        assert_eq!(vec![(1..=2, 5..=7)], parse_pairs("1-2,5-7"));
+        assert_eq!(vec![group(1, &[(1, 2), (5, 7)])], parse_groups("1-2,5-7"));
         assert_eq!(
-            vec![(1..=2, 5..=7), (8..=9, 8..=11)],
-            parse_pairs("1-2,5-7\n8-9,8-11")
+            vec![
+                group(1, &[(1, 2), (5, 7)]),
+                group(3, &[(8, 9), (8, 11), (1, 1)])
+            ],
+            parse_groups("1-2,5-7\n\n8-9,8-11,1-1")
         );
     }
 
     #[test]
     fn find_fully_contained_test() {
         assert_eq!(
-            vec![(1..=2, 1..=4)],
-            find_fully_contained(vec![(1..=2, 1..=4), (1..=2, 3..=4)])
+            vec![1],
+            find_fully_contained(&[group(1, &[(1, 2), (1, 4)]), group(2, &[(1, 2), (3, 4)])])
         );
         assert_eq!(
-            vec![(1..=2, 1..=4), (5..=8, 8..=8)],
-            find_fully_contained(vec![(1..=2, 1..=4), (5..=8, 8..=8)])
+            vec![1, 2],
+            find_fully_contained(&[group(1, &[(1, 2), (1, 4)]), group(2, &[(5, 8), (8, 8)])])
         );
     }
 
@@ -102,9 +213,29 @@ mod test {
     #[test]
     fn find_partly_contained_test() {
         assert_eq!(
-            vec![(5..=7, 7..=9)],
-            find_partly_contained(vec![(5..=7, 7..=9), (1..=2, 3..=5)])
+            vec![1],
+            find_partly_contained(&[group(1, &[(5, 7), (7, 9)]), group(2, &[(1, 2), (3, 5)])])
+        );
+    }
+
+    #[test]
+    fn huge_ranges() {
+        let input = "1-1000000000,999999999-2000000000\n5-999999999,1-1000000000";
+        assert_eq!(Solution::I32(1), solve1(input));
+        assert_eq!(Solution::I32(2), solve2(input));
+    }
+
+    #[test]
+    fn larger_groups() {
+        let mut groups = parse_groups(
+            "1-10,2-3,4-9\n1-3,5-6,2-8\n1-2,4-5,7-8\n3-5,1-4,4-9,4-4\n1-2,3-4,5-6,2-5",
         );
+        assert_eq!(vec![1], find_fully_contained(&groups));
+        assert_eq!(vec![1, 2, 4, 5], find_partly_contained(&groups));
+        assert_eq!(vec![4], find_common_point(&groups));
+        assert_eq!((4, vec![4]), max_depth(&groups));
+        groups.remove(3);
+        assert_eq!((2, vec![1, 2, 5]), max_depth(&groups));
     }
 
     #[test]
@@ -1,7 +1,12 @@
-use std::collections::HashMap;
+use std::{
+    fmt::{self, Display},
+    io::{self, BufRead, Write},
+};
 
 use crate::{
+    cli::Args,
     input::get_input,
+    problem::Problem,
     solution::{Solution, SolutionPair},
 };
 
@@ -13,221 +18,686 @@ pub fn solve() -> SolutionPair {
 }
 
 fn solve1(input: &str) -> Solution {
-    solver(input, false)
+    solver(input, &CrateMover9000)
 }
 
 fn solve2(input: &str) -> Solution {
-    solver(input, true)
+    solver(input, &CrateMover9001)
 }
 
-fn solver(input: &str, at_once: bool) -> Solution {
-    let (mut stack, mut moves) = parse_input(input);
-    loop {
-        (stack, moves) = advance_stack((stack.clone(), moves.clone()), at_once);
-        if moves.is_empty() {
-            return Solution::String(code(stack));
+fn solver(input: &str, crane: &dyn Crane) -> Solution {
+    let (mut stack, moves) = parse_input(input).unwrap_or_else(|e| panic!("{}", e));
+    for m in &moves {
+        advance_stack(&mut stack, m, crane).unwrap_or_else(|e| panic!("{}", e));
+    }
+    Solution::String(code(&stack))
+}
+
+pub fn tool(args: &Args) {
+    let crane = args
+        .value::<String>("crane")
+        .map(|name| parse_crane(&name).unwrap_or_else(|| panic!("unknown crane {}", name)))
+        .unwrap_or(Box::new(CrateMover9001));
+    let (mut stack, moves) = parse_input(&args.input(DAY)).unwrap_or_else(|e| panic!("{}", e));
+    if args.flag("replay") {
+        let mut history = History::new(stack, moves, crane.as_ref());
+        history.run(io::stdin().lock(), &mut io::stdout()).unwrap();
+        return;
+    }
+    if let Some(n) = args.value("jump") {
+        let mut history = History::new(stack, moves, crane.as_ref());
+        history.jump(n).unwrap_or_else(|e| panic!("{}", e));
+        history.show(&mut io::stdout()).unwrap();
+        return;
+    }
+    if let Some(c) = args.value("top") {
+        let target = args.value("stack").expect("--top needs a --stack");
+        let mut history = History::new(stack, moves, crane.as_ref());
+        match history.first_on_top(c, target) {
+            Ok(Some(0)) => println!("{} starts on top of stack {}", c, target),
+            Ok(Some(n)) => println!("{} reaches the top of stack {} at move {}", c, target, n),
+            Ok(None) => println!("{} never reaches the top of stack {}", c, target),
+            Err(e) => panic!("{}", e),
+        }
+        return;
+    }
+    let draw = args.flag("draw");
+    if draw {
+        println!("{}", render(&stack));
+    }
+    for m in &moves {
+        advance_stack(&mut stack, m, crane.as_ref()).unwrap_or_else(|e| panic!("{}", e));
+        if draw {
+            println!("{}\n{}", m, render(&stack));
         }
     }
+    if args.flag("serialize") {
+        print!("{}", serialize(&stack, &[]));
+    }
+    println!("{}", code(&stack));
+}
+
+// how a crane takes `count` crates off a stack, returned in the order they are put down
+trait Crane {
+    fn lift(&self, source: &mut Vec<char>, count: usize) -> Vec<char>;
+    // puts lifted crates back where they came from, undoing `lift`
+    fn unlift(&self, source: &mut Vec<char>, crates: Vec<char>);
+}
+
+// moves one crate at a time
+struct CrateMover9000;
+
+impl Crane for CrateMover9000 {
+    fn lift(&self, source: &mut Vec<char>, count: usize) -> Vec<char> {
+        let mut crates = source.split_off(source.len() - count);
+        crates.reverse();
+        crates
+    }
+
+    fn unlift(&self, source: &mut Vec<char>, crates: Vec<char>) {
+        source.extend(crates.into_iter().rev());
+    }
 }
 
-type Stack = HashMap<i32, Vec<String>>;
-type Move = (i32, i32, i32);
-type Moves = Vec<Move>;
+// moves all the crates at once, keeping their order
+struct CrateMover9001;
 
-fn advance_stack((mut stack, moves): (Stack, Moves), at_once: bool) -> (Stack, Moves) {
-    assert!(
-        !moves.is_empty(),
-        "Cannot advance a stack with no moves, bro"
-    );
-    let (number, from, to) = moves.first().unwrap();
+impl Crane for CrateMover9001 {
+    fn lift(&self, source: &mut Vec<char>, count: usize) -> Vec<char> {
+        source.split_off(source.len() - count)
+    }
 
-    let source_stack = stack.get(from).unwrap_or_else(|| {
-        panic!("unwrapped failed, ({} {} {})", number, from, to);
-    });
-    let (mut to_move, rest) = (
-        source_stack[0..*number as usize].to_vec(),
-        source_stack[*number as usize..source_stack.len()].to_vec(),
-    );
-    if at_once {
-        to_move.reverse();
+    fn unlift(&self, source: &mut Vec<char>, crates: Vec<char>) {
+        source.extend(crates);
     }
-    stack.insert(*from, rest);
-    for e in to_move {
-        stack.entry(*to).and_modify(|entry| entry.insert(0, e));
+}
+
+// moves at most `capacity` crates at once, each lift keeps its order
+struct CapacityCrane {
+    capacity: usize,
+}
+
+impl Crane for CapacityCrane {
+    fn lift(&self, source: &mut Vec<char>, count: usize) -> Vec<char> {
+        let mut crates = Vec::with_capacity(count);
+        let mut remaining = count;
+        while remaining > 0 {
+            let lift = remaining.min(self.capacity.max(1));
+            crates.extend(source.split_off(source.len() - lift));
+            remaining -= lift;
+        }
+        crates
     }
 
-    (stack, moves[1..moves.len()].to_vec())
+    // every lift but the last is a full one, the first lift came off the top
+    fn unlift(&self, source: &mut Vec<char>, crates: Vec<char>) {
+        for lift in crates.chunks(self.capacity.max(1)).rev() {
+            source.extend_from_slice(lift);
+        }
+    }
 }
 
-fn parse_input(input: &str) -> (Stack, Moves) {
-    let parts = input
-        .split("\n\n")
-        .into_iter()
-        .filter(|l| !l.is_empty())
-        .collect::<Vec<_>>();
-    (parse_stack(parts[0]), parse_moves(parts[1]))
+// pulls the crates out from the bottom of the stack, keeping their order
+struct BottomCrane;
+
+impl Crane for BottomCrane {
+    fn lift(&self, source: &mut Vec<char>, count: usize) -> Vec<char> {
+        source.drain(..count).collect()
+    }
+
+    fn unlift(&self, source: &mut Vec<char>, crates: Vec<char>) {
+        source.splice(..0, crates);
+    }
 }
 
-fn parse_stack(input: &str) -> Stack {
-    input
-        .split("\n")
-        .filter(|l| l.contains("["))
-        .fold(
-            &mut HashMap::new(),
-            |acc: &mut HashMap<i32, Vec<String>>, line| {
-                let line = line
-                    .char_indices()
-                    .filter(|(i, _)| i % 4 == 1)
-                    .map(|(_, c)| c)
-                    .collect::<Vec<_>>()
-                    .iter()
-                    .enumerate()
-                    .map(|(i, c)| (i + 1, c.to_string()))
-                    .filter(|(_, c)| !c.trim().is_empty())
-                    .collect::<Vec<_>>();
-
-                for (i, val) in line {
-                    acc.entry(i as i32)
-                        .and_modify(|e| e.push(val.to_string()))
-                        .or_insert(vec![val.to_string()]);
+// 9000, 9001, bottom or capacity=N
+fn parse_crane(name: &str) -> Option<Box<dyn Crane>> {
+    match name.split_once('=') {
+        None if name == "9000" => Some(Box::new(CrateMover9000)),
+        None if name == "9001" => Some(Box::new(CrateMover9001)),
+        None if name == "bottom" => Some(Box::new(BottomCrane)),
+        Some(("capacity", n)) => n
+            .parse()
+            .ok()
+            .filter(|&capacity| capacity > 0)
+            .map(|capacity| Box::new(CapacityCrane { capacity }) as Box<dyn Crane>),
+        _ => None,
+    }
+}
+
+// stack n is at index n - 1, the top crate is the last element
+type Stack = Vec<Vec<char>>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Move {
+    count: usize,
+    from: usize,
+    to: usize,
+    // line of the move in the input, for error reporting
+    line: usize,
+}
+
+impl Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "move {} from {} to {}", self.count, self.from, self.to)
+    }
+}
+
+fn advance_stack(stack: &mut Stack, m: &Move, crane: &dyn Crane) -> Result<(), Problem> {
+    let problem = |message| Problem::new(m.line, message);
+    for s in [m.from, m.to] {
+        if s == 0 || s > stack.len() {
+            return Err(problem(format!("there is no stack {}", s)));
+        }
+    }
+    let source = &mut stack[m.from - 1];
+    if m.count > source.len() {
+        return Err(problem(format!(
+            "cannot move {} crates from stack {} holding {}",
+            m.count,
+            m.from,
+            source.len()
+        )));
+    }
+    let to_move = crane.lift(source, m.count);
+    stack[m.to - 1].extend(to_move);
+    Ok(())
+}
+
+// what is needed to take back a move, the moved crates sit on top of the target
+struct Undo {
+    from: usize,
+    to: usize,
+    crates: Vec<char>,
+}
+
+// the simulation as a position in the list of moves that can be moved in both directions
+struct History<'a> {
+    stack: Stack,
+    moves: Vec<Move>,
+    crane: &'a dyn Crane,
+    // one entry per applied move, so its length is the number of moves done
+    log: Vec<Undo>,
+}
+
+impl<'a> History<'a> {
+    fn new(stack: Stack, moves: Vec<Move>, crane: &'a dyn Crane) -> Self {
+        Self {
+            stack,
+            moves,
+            crane,
+            log: vec![],
+        }
+    }
+
+    fn position(&self) -> usize {
+        self.log.len()
+    }
+
+    // applies the next move, false once all of them are done
+    fn forward(&mut self) -> Result<bool, Problem> {
+        let Some(m) = self.moves.get(self.position()) else {
+            return Ok(false);
+        };
+        advance_stack(&mut self.stack, m, self.crane)?;
+        let target = &self.stack[m.to - 1];
+        self.log.push(Undo {
+            from: m.from - 1,
+            to: m.to - 1,
+            crates: target[target.len() - m.count..].to_vec(),
+        });
+        Ok(true)
+    }
+
+    // takes back the last move, false when at the start
+    fn back(&mut self) -> bool {
+        let Some(undo) = self.log.pop() else {
+            return false;
+        };
+        let target = &mut self.stack[undo.to];
+        target.truncate(target.len() - undo.crates.len());
+        self.crane.unlift(&mut self.stack[undo.from], undo.crates);
+        true
+    }
+
+    // moves to the state after `n` moves
+    fn jump(&mut self, n: usize) -> Result<(), Problem> {
+        while self.position() > n {
+            self.back();
+        }
+        while self.position() < n {
+            if !self.forward()? {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    // number of moves after which `c` is first on top of stack `target`, 0 if it starts there
+    fn first_on_top(&mut self, c: char, target: usize) -> Result<Option<usize>, Problem> {
+        let position = self.position();
+        self.jump(0)?;
+        let on_top = |history: &Self| {
+            history
+                .stack
+                .get(target.wrapping_sub(1))
+                .and_then(|s| s.last())
+                == Some(&c)
+        };
+        let mut found = on_top(self).then_some(0);
+        while found.is_none() && self.forward()? {
+            found = on_top(self).then_some(self.position());
+        }
+        self.jump(position)?;
+        Ok(found)
+    }
+
+    fn run(&mut self, input: impl BufRead, out: &mut impl Write) -> io::Result<()> {
+        self.show(out)?;
+        for line in input.lines() {
+            let line = line?;
+            let words: Vec<_> = line.split_whitespace().collect();
+            let result = match words.as_slice() {
+                [] => continue,
+                ["next" | "n", count @ ..] | ["back" | "b", count @ ..] => {
+                    let count = count.first().and_then(|c| c.parse().ok()).unwrap_or(1);
+                    let target = if words[0].starts_with('n') {
+                        self.position() + count
+                    } else {
+                        self.position().saturating_sub(count)
+                    };
+                    self.jump(target)
+                }
+                ["jump" | "j", n] => match n.parse() {
+                    Ok(n) => self.jump(n),
+                    Err(_) => {
+                        writeln!(out, "invalid move number {}", n)?;
+                        continue;
+                    }
+                },
+                ["top", c, target] => {
+                    match (c.parse(), target.parse()) {
+                        (Ok(c), Ok(target)) => match self.first_on_top(c, target) {
+                            Ok(Some(n)) => writeln!(out, "move {}", n)?,
+                            Ok(None) => writeln!(out, "never")?,
+                            Err(e) => writeln!(out, "{}", e)?,
+                        },
+                        _ => writeln!(out, "usage: top CRATE STACK")?,
+                    }
+                    continue;
+                }
+                ["show" | "s"] => Ok(()),
+                ["quit" | "q"] => break,
+                _ => {
+                    writeln!(out, "unknown command {}", line)?;
+                    continue;
                 }
-                acc
-            },
+            };
+            if let Err(e) = result {
+                writeln!(out, "{}", e)?;
+            }
+            self.show(out)?;
+        }
+        Ok(())
+    }
+
+    fn show(&self, out: &mut impl Write) -> io::Result<()> {
+        match self.position() {
+            0 => writeln!(out, "start of {} moves", self.moves.len())?,
+            n => writeln!(
+                out,
+                "move {}/{}: {}",
+                n,
+                self.moves.len(),
+                self.moves[n - 1]
+            )?,
+        }
+        write!(out, "{}", render(&self.stack))
+    }
+}
+
+fn parse_input(input: &str) -> Result<(Stack, Vec<Move>), Problem> {
+    let (drawing, moves) = input.split_once("\n\n").ok_or_else(|| {
+        Problem::new(
+            input.lines().count(),
+            "expected a blank line between the stacks and the moves",
         )
-        .to_owned()
+    })?;
+    let first_move_line = drawing.lines().count() + 2;
+    Ok((parse_stack(drawing)?, parse_moves(moves, first_move_line)?))
 }
 
-fn parse_moves(input: &str) -> Moves {
+// the label line at the bottom decides how many stacks there are, trailing spaces may be
+// trimmed off the crate lines above it
+fn parse_stack(input: &str) -> Result<Stack, Problem> {
+    let lines: Vec<_> = input.lines().collect();
+    let label_line = lines.len();
+    let Some((labels, crates)) = lines.split_last() else {
+        return Err(Problem::new(
+            1,
+            "expected stacks with a label line below them",
+        ));
+    };
+    for (i, label) in labels.split_whitespace().enumerate() {
+        if label.parse() != Ok(i + 1) {
+            return Err(Problem::new(
+                label_line,
+                format!("expected stack label {}, got {}", i + 1, label),
+            ));
+        }
+    }
+    let mut stack = vec![vec![]; labels.split_whitespace().count()];
+    for (row, line) in crates.iter().enumerate().rev() {
+        for (i, c) in line.chars().enumerate().filter(|(i, _)| i % 4 == 1) {
+            if c.is_whitespace() {
+                continue;
+            }
+            match stack.get_mut(i / 4) {
+                Some(s) => s.push(c),
+                None => {
+                    return Err(Problem::new(
+                        row + 1,
+                        format!("crate {} is not above a labelled stack", c),
+                    ))
+                }
+            }
+        }
+    }
+    Ok(stack)
+}
+
+fn parse_moves(input: &str, first_line: usize) -> Result<Vec<Move>, Problem> {
     input
-        .split("\n")
-        .filter(|l| !l.is_empty())
-        .map(|l| {
-            let nums: Vec<i32> = l
-                .split(" ")
-                .filter(|segment| !vec!["move", "from", "to"].contains(segment))
-                .map(|num| num.parse::<i32>().unwrap())
-                .collect();
-            (nums[0], nums[1], nums[2])
+        .lines()
+        .enumerate()
+        .filter(|(_, l)| !l.trim().is_empty())
+        .map(|(i, l)| {
+            let line = first_line + i;
+            let nums = match l.split_whitespace().collect::<Vec<_>>().as_slice() {
+                ["move", count, "from", from, "to", to] => {
+                    [count, from, to].map(|n| n.parse().ok())
+                }
+                _ => [None; 3],
+            };
+            match nums {
+                [Some(count), Some(from), Some(to)] => Ok(Move {
+                    count,
+                    from,
+                    to,
+                    line,
+                }),
+                _ => Err(Problem::new(
+                    line,
+                    format!("expected `move N from A to B`, got {}", l),
+                )),
+            }
         })
         .collect()
 }
 
-fn code(stack: Stack) -> String {
-    let mut stack_v = stack.iter().collect::<Vec<_>>();
-    stack_v.sort_by_key(|(k, _)| **k);
-    stack_v
-        .iter()
-        .map(|(_, v)| v.first().map(|s| s.to_string()).unwrap_or("".to_string()))
-        .collect::<Vec<_>>()
-        .join("")
+fn code(stack: &Stack) -> String {
+    stack.iter().filter_map(|s| s.last()).collect()
+}
+
+// draws the stacks the way the puzzle does, including the row of stack numbers
+fn render(stack: &Stack) -> String {
+    let height = stack.iter().map(|s| s.len()).max().unwrap_or(0);
+    let mut out = String::new();
+    for row in (0..height).rev() {
+        let cells: Vec<_> = stack
+            .iter()
+            .map(|s| match s.get(row) {
+                Some(c) => format!("[{}]", c),
+                None => "   ".to_string(),
+            })
+            .collect();
+        out.push_str(&cells.join(" "));
+        out.push('\n');
+    }
+    let labels: Vec<_> = (1..=stack.len()).map(|i| format!(" {} ", i)).collect();
+    out.push_str(&labels.join(" "));
+    out.push('\n');
+    out
+}
+
+// writes a puzzle input that `parse_input` reads back into the same stacks and moves
+fn serialize(stack: &Stack, moves: &[Move]) -> String {
+    let moves: Vec<_> = moves.iter().map(|m| m.to_string()).collect();
+    format!("{}\n{}", render(stack), moves.join("\n"))
 }
 
 #[cfg(test)]
 mod test {
-    use std::collections::HashMap;
-
     use crate::{input::get_input, solution::Solution};
 
-    use super::{advance_stack, code, parse_input, solve1, solve2, DAY};
+    use super::{
+        advance_stack, code, parse_crane, parse_input, render, serialize, solve1, solve2,
+        CrateMover9000, CrateMover9001, History, Move, Problem, Stack, DAY,
+    };
 
     fn sample_input() -> String {
         get_input(DAY, true, None)
     }
 
+    fn sample_stack() -> Stack {
+        vec![vec!['Z', 'N'], vec!['M', 'C', 'D'], vec!['P']]
+    }
+
+    fn mv(count: usize, from: usize, to: usize, line: usize) -> Move {
+        Move {
+            count,
+            from,
+            to,
+            line,
+        }
+    }
+
     #[test]
     fn parse_test() {
         assert_eq!(
-            (
-                HashMap::from([
-                    (1i32, vec!["N".to_string(), "Z".to_string()]),
-                    (
-                        2i32,
-                        vec!["D".to_string(), "C".to_string(), "M".to_string()]
-                    ),
-                    (3i32, vec!["P".to_string()])
-                ]),
-                vec![(1, 2, 1), (3, 1, 3), (2, 2, 1), (1, 1, 2),]
-            ),
+            Ok((
+                sample_stack(),
+                vec![
+                    mv(1, 2, 1, 6),
+                    mv(3, 1, 3, 7),
+                    mv(2, 2, 1, 8),
+                    mv(1, 1, 2, 9)
+                ]
+            )),
             parse_input(&sample_input())
         )
     }
 
     #[test]
-    fn test_advance() {
-        let (stack, moves) = (
-            HashMap::from([
-                (1i32, vec!["N".to_string(), "Z".to_string()]),
-                (
-                    2i32,
-                    vec!["D".to_string(), "C".to_string(), "M".to_string()],
-                ),
-                (3i32, vec!["P".to_string()]),
-            ]),
-            vec![(1, 2, 1), (3, 1, 3), (2, 2, 1), (1, 1, 2)],
+    fn stack_labels() {
+        // the last stack is empty and the crate lines have their trailing spaces trimmed
+        let (stack, moves) =
+            parse_input("[A]\n[B] [C]\n 1   2   3\n\nmove 1 from 1 to 3\n").unwrap();
+        assert_eq!(vec![vec!['B', 'A'], vec!['C'], vec![]], stack);
+        assert_eq!(vec![mv(1, 1, 3, 5)], moves);
+        assert_eq!(
+            Err(Problem::new(2, "expected stack label 2, got 3")),
+            parse_input("[A]     [C]\n 1   3 \n\n")
         );
         assert_eq!(
-            (
-                HashMap::from([
-                    (
-                        1i32,
-                        vec!["D".to_string(), "N".to_string(), "Z".to_string()]
-                    ),
-                    (2i32, vec!["C".to_string(), "M".to_string()],),
-                    (3i32, vec!["P".to_string()]),
-                ]),
-                vec![(3, 1, 3), (2, 2, 1), (1, 1, 2)],
-            ),
-            advance_stack((stack, moves), false)
+            Err(Problem::new(1, "crate B is not above a labelled stack")),
+            parse_input("[A] [B]\n 1 \n\n")
         );
     }
 
+    #[test]
+    fn test_advance() {
+        let mut stack = sample_stack();
+        advance_stack(&mut stack, &mv(1, 2, 1, 6), &CrateMover9000).unwrap();
+        assert_eq!(vec![vec!['Z', 'N', 'D'], vec!['M', 'C'], vec!['P']], stack);
+    }
+
     #[test]
     fn test_advance_at_once() {
-        let (stack, moves) = (
-            HashMap::from([
-                (1i32, vec!["N".to_string(), "Z".to_string()]),
-                (
-                    2i32,
-                    vec!["D".to_string(), "C".to_string(), "M".to_string()],
-                ),
-                (3i32, vec!["P".to_string()]),
-            ]),
-            vec![(3, 2, 1), (2, 2, 1), (1, 1, 2)],
-        );
+        let mut stack = sample_stack();
+        advance_stack(&mut stack, &mv(3, 2, 1, 6), &CrateMover9001).unwrap();
         assert_eq!(
-            (
-                HashMap::from([
-                    (
-                        1i32,
-                        vec![
-                            "D".to_string(),
-                            "C".to_string(),
-                            "M".to_string(),
-                            "N".to_string(),
-                            "Z".to_string()
-                        ]
-                    ),
-                    (2i32, vec![],),
-                    (3i32, vec!["P".to_string()]),
-                ]),
-                vec![(2, 2, 1), (1, 1, 2)],
-            ),
-            advance_stack((stack, moves), true)
+            vec![vec!['Z', 'N', 'M', 'C', 'D'], vec![], vec!['P']],
+            stack
         );
     }
 
     #[test]
     fn stack_code_test() {
+        assert_eq!("NDP", code(&sample_stack()));
+    }
+
+    #[test]
+    fn invalid_moves() {
+        let mut stack = sample_stack();
+        assert_eq!(
+            Err(Problem::new(
+                7,
+                "cannot move 3 crates from stack 1 holding 2"
+            )),
+            advance_stack(&mut stack, &mv(3, 1, 2, 7), &CrateMover9000)
+        );
+        assert_eq!(
+            Err(Problem::new(8, "there is no stack 4")),
+            advance_stack(&mut stack, &mv(1, 1, 4, 8), &CrateMover9000)
+        );
+        assert_eq!(sample_stack(), stack);
+        assert_eq!(
+            Err(Problem::new(
+                7,
+                "expected `move N from A to B`, got move 3 from one to 3"
+            )),
+            parse_input(&sample_input().replace("from 1 to 3", "from one to 3"))
+        );
+    }
+
+    #[test]
+    fn other_cranes() {
+        let lift = |crane: &str| {
+            let mut source = vec!['A', 'B', 'C', 'D', 'E'];
+            let lifted = parse_crane(crane).unwrap().lift(&mut source, 4);
+            (lifted.into_iter().collect::<String>(), source)
+        };
+        assert_eq!(("EDCB".to_string(), vec!['A']), lift("9000"));
+        assert_eq!(("BCDE".to_string(), vec!['A']), lift("9001"));
+        assert_eq!(("DEBC".to_string(), vec!['A']), lift("capacity=2"));
+        assert_eq!(("CDEB".to_string(), vec!['A']), lift("capacity=3"));
+        assert_eq!(("ABCD".to_string(), vec!['E']), lift("bottom"));
+        assert!(parse_crane("capacity=0").is_none());
+        assert!(parse_crane("9002").is_none());
+    }
+
+    #[test]
+    fn render_stacks() {
+        assert_eq!(
+            "    [D]    \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 \n",
+            render(&sample_stack())
+        );
+        assert_eq!(" 1   2 \n", render(&vec![vec![], vec![]]));
+    }
+
+    #[test]
+    fn serialize_round_trip() {
+        let input = sample_input();
+        let (stack, moves) = parse_input(&input).unwrap();
+        assert_eq!(input, serialize(&stack, &moves));
+
+        let input = get_input(DAY, false, None);
+        let (stack, moves) = parse_input(&input).unwrap();
+        assert_eq!(
+            Ok((stack.clone(), moves.clone())),
+            parse_input(&serialize(&stack, &moves))
+        );
+
+        let mut stack = sample_stack();
+        stack[1].clear();
+        let (parsed, _) = parse_input(&serialize(&stack, &[])).unwrap();
+        assert_eq!(stack, parsed);
+    }
+
+    #[test]
+    fn history_undo_redo() {
+        let (stack, mut moves) = parse_input(&sample_input()).unwrap();
+        // a move onto its own stack and one that the capacity crane needs several lifts for
+        moves.push(Move {
+            count: 2,
+            from: 3,
+            to: 3,
+            line: 10,
+        });
+        moves.push(Move {
+            count: 3,
+            from: 3,
+            to: 1,
+            line: 11,
+        });
+        for name in ["9000", "9001", "bottom", "capacity=2"] {
+            let crane = parse_crane(name).unwrap();
+            let mut history = History::new(stack.clone(), moves.clone(), crane.as_ref());
+            let mut states = vec![stack.clone()];
+            while history.forward().unwrap() {
+                states.push(history.stack.clone());
+            }
+            assert_eq!(6, history.position());
+            while history.back() {
+                assert_eq!(states[history.position()], history.stack, "{}", name);
+            }
+            history.jump(3).unwrap();
+            assert_eq!(states[3], history.stack);
+            history.jump(1).unwrap();
+            assert_eq!(states[1], history.stack);
+            history.jump(10).unwrap();
+            assert_eq!(6, history.position());
+        }
+    }
+
+    #[test]
+    fn first_on_top() {
+        let (stack, moves) = parse_input(&sample_input()).unwrap();
+        let mut history = History::new(stack, moves, &CrateMover9000);
+        history.jump(2).unwrap();
+        assert_eq!(Ok(Some(0)), history.first_on_top('D', 2));
+        assert_eq!(Ok(Some(1)), history.first_on_top('D', 1));
+        assert_eq!(Ok(Some(2)), history.first_on_top('Z', 3));
+        assert_eq!(Ok(Some(4)), history.first_on_top('M', 2));
+        assert_eq!(Ok(None), history.first_on_top('P', 1));
+        assert_eq!(2, history.position());
+    }
+
+    #[test]
+    fn replay_commands() {
+        let (stack, moves) = parse_input(&sample_input()).unwrap();
+        let mut history = History::new(stack, moves, &CrateMover9001);
+        let mut out = vec![];
+        history
+            .run("n 2\nb\ntop M 3\nj 9\nq\n".as_bytes(), &mut out)
+            .unwrap();
         assert_eq!(
-            "NDP",
-            code(HashMap::from([
-                (1i32, vec!["N".to_string(), "Z".to_string()]),
-                (
-                    2i32,
-                    vec!["D".to_string(), "C".to_string(), "M".to_string()],
-                ),
-                (3i32, vec!["P".to_string()]),
-            ]))
+            "start of 4 moves
+    [D]    
+[N] [C]    
+[Z] [M] [P]
+ 1   2   3 
+move 2/4: move 3 from 1 to 3
+        [D]
+        [N]
+    [C] [Z]
+    [M] [P]
+ 1   2   3 
+move 1/4: move 1 from 2 to 1
+[D]        
+[N] [C]    
+[Z] [M] [P]
+ 1   2   3 
+never
+move 4/4: move 1 from 1 to 2
+        [D]
+        [N]
+        [Z]
+[M] [C] [P]
+ 1   2   3 
+",
+            String::from_utf8(out).unwrap()
         );
     }
 
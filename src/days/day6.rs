@@ -1,6 +1,10 @@
-use std::collections::HashSet;
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader, Cursor},
+};
 
 use crate::{
+    cli::Args,
     input::get_input,
     solution::{Solution, SolutionPair},
 };
@@ -13,31 +17,132 @@ pub fn solve() -> SolutionPair {
 }
 
 fn solve1(input: &str) -> Solution {
-    Solution::I32(index_of_first_n_unique::<4>(&parse_input(input)))
+    Solution::I32(first_marker(input, 4) as i32)
 }
 
 fn solve2(input: &str) -> Solution {
-    Solution::I32(index_of_first_n_unique::<14>(&parse_input(input)))
+    Solution::I32(first_marker(input, 14) as i32)
+}
+
+// `--input -` reads stdin, any other `--input` is streamed from disk instead of loaded
+pub fn tool(args: &Args) {
+    let window = args.value("window").unwrap_or(4);
+    let reader: Box<dyn BufRead> = match args.value::<String>("input").as_deref() {
+        Some("-") => Box::new(io::stdin().lock()),
+        Some(path) => Box::new(BufReader::new(
+            File::open(path).unwrap_or_else(|e| panic!("cannot read {}: {}", path, e)),
+        )),
+        None => Box::new(Cursor::new(args.input(DAY))),
+    };
+    let mut markers = Markers::new(reader, window);
+    if args.flag("all") {
+        for marker in markers {
+            println!("{}", marker.unwrap());
+        }
+    } else {
+        match markers.next() {
+            Some(marker) => println!("{}", marker.unwrap()),
+            None => println!("no marker of {} distinct characters", window),
+        }
+    }
+}
+
+fn first_marker(input: &str, window: usize) -> usize {
+    Markers::new(input.as_bytes(), window)
+        .next()
+        .expect("no marker found")
+        .unwrap()
 }
 
-fn parse_input(input: &str) -> Vec<char> {
-    input.chars().collect()
+// yields the number of bytes read whenever the last `window` of them are all different,
+// line breaks are not part of the stream
+struct Markers<R> {
+    reader: R,
+    window: Window,
 }
 
-fn index_of_first_n_unique<const N: usize>(chars: &[char]) -> i32 {
-    chars
-        .array_windows::<N>()
-        .enumerate()
-        .find(|(_, w)| HashSet::from(**w).len() == N)
-        .map(|(i, _)| i + N)
-        .unwrap() as i32
+impl<R: BufRead> Markers<R> {
+    fn new(reader: R, size: usize) -> Self {
+        assert!(size > 0, "the window needs at least one character");
+        Self {
+            reader,
+            window: Window {
+                size,
+                recent: vec![0; size],
+                counts: [0; 256],
+                repeated: 0,
+                read: 0,
+            },
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for Markers<R> {
+    type Item = io::Result<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let buf = match self.reader.fill_buf() {
+                Ok([]) => return None,
+                Ok(buf) => buf,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Some(Err(e)),
+            };
+            let mut consumed = 0;
+            let mut found = false;
+            for &byte in buf {
+                consumed += 1;
+                if byte != b'\n' && byte != b'\r' && self.window.push(byte) {
+                    found = true;
+                    break;
+                }
+            }
+            self.reader.consume(consumed);
+            if found {
+                return Some(Ok(self.window.read));
+            }
+        }
+    }
+}
+
+struct Window {
+    size: usize,
+    // the last `size` bytes, byte i of the stream is at i % size
+    recent: Vec<u8>,
+    counts: [u32; 256],
+    // number of byte values seen more than once in the window
+    repeated: usize,
+    read: usize,
+}
+
+impl Window {
+    // adds a byte to the window, true if it is now full of distinct bytes
+    fn push(&mut self, byte: u8) -> bool {
+        let slot = self.read % self.size;
+        if self.read >= self.size {
+            let old = self.recent[slot] as usize;
+            if self.counts[old] == 2 {
+                self.repeated -= 1;
+            }
+            self.counts[old] -= 1;
+        }
+        self.counts[byte as usize] += 1;
+        if self.counts[byte as usize] == 2 {
+            self.repeated += 1;
+        }
+        self.recent[slot] = byte;
+        self.read += 1;
+        self.read >= self.size && self.repeated == 0
+    }
 }
 
 #[cfg(test)]
 mod test {
+    use std::io::BufReader;
+
     use crate::{input::get_input, solution::Solution};
 
-    use super::{solve1, solve2, DAY};
+    use super::{solve1, solve2, Markers, DAY};
 
     fn sample_input(qualifier: Option<&str>) -> String {
         get_input(DAY, true, qualifier)
@@ -54,4 +159,30 @@ mod test {
         assert_eq!(Solution::I32(19), solve2(&sample_input(None)));
         assert_eq!(Solution::I32(23), solve2(&sample_input("2".into())));
     }
+
+    #[test]
+    fn all_markers() {
+        let markers = |input: &str, window| {
+            Markers::new(input.as_bytes(), window)
+                .map(Result::unwrap)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(vec![3, 4, 7, 8], markers("abcaaxyz", 3));
+        assert_eq!(vec![1, 2, 3], markers("aa\nb", 1));
+        assert_eq!(Vec::<usize>::new(), markers("abab", 3));
+        assert_eq!(vec![4, 5], markers("ab\ncd\ne\n", 4));
+    }
+
+    #[test]
+    fn small_reads() {
+        // a one byte buffer makes every marker straddle a refill
+        let input = get_input(DAY, false, None);
+        let expected: Vec<_> = Markers::new(input.as_bytes(), 14)
+            .map(Result::unwrap)
+            .collect();
+        let reader = BufReader::with_capacity(1, input.as_bytes());
+        let actual: Vec<_> = Markers::new(reader, 14).map(Result::unwrap).collect();
+        assert!(!expected.is_empty());
+        assert_eq!(expected, actual);
+    }
 }
@@ -1,7 +1,9 @@
-use std::{collections::HashMap, fmt::Debug};
+use std::collections::HashMap;
 
 use crate::{
+    cli::Args,
     input::get_input,
+    problem::Problem,
     solution::{Solution, SolutionPair},
 };
 
@@ -14,45 +16,108 @@ pub fn solve() -> SolutionPair {
 
 fn solve1(input: &str) -> Solution {
     let fs = FS::build_from_input(input);
-    let sum_dirs_gt_100k: usize = fs
-        .dirs
-        .iter()
-        .map(|(name, _)| fs.dir_size(name))
-        .filter(|size| size <= &100000)
-        .sum();
-    Solution::I32(sum_dirs_gt_100k as i32)
+    Solution::I32(fs.puzzle_answers().0 as i32)
 }
 
 fn solve2(input: &str) -> Solution {
-    const REQUIRED_SPACE: usize = 30000000;
-    const TOTAL_SPACE: usize = 70000000;
-
     let fs = FS::build_from_input(input);
+    let size = fs
+        .puzzle_answers()
+        .1
+        .expect("no directory frees enough space");
+    Solution::I32(size as i32)
+}
 
-    let unused_space = TOTAL_SPACE - fs.dir_size("/");
-    let to_free = REQUIRED_SPACE - unused_space;
+pub fn tool(args: &Args) {
+    if let Some(num_dirs) = args.value("synthetic-dirs") {
+        let num_files = args.value("synthetic-files").unwrap_or(num_dirs * 4);
+        let fs = FS::synthetic(num_dirs, num_files, args.value("seed").unwrap_or(2022));
+        print!("{}", fs.transcript());
+        return;
+    }
+    let input = args.input(DAY);
+    let fs = if args.flag("strict") {
+        match FS::build_strict(&input) {
+            Ok(fs) => fs,
+            Err(problems) => {
+                for problem in problems {
+                    println!("{}", problem);
+                }
+                return;
+            }
+        }
+    } else {
+        FS::build_from_input(&input)
+    };
+    if args.flag("transcript") {
+        print!("{}", fs.transcript());
+    }
+    if args.flag("tree") {
+        print!("{}", fs.tree());
+    }
+    if args.flag("du") {
+        print!("{}", fs.du());
+    }
+    if args.flag("json") {
+        let root = match args.value::<String>("path") {
+            Some(path) => fs
+                .lookup(&path)
+                .unwrap_or_else(|| panic!("no such file or directory {}", path)),
+            None => ROOT,
+        };
+        println!("{}", fs.json(root));
+    }
+    if let Some(n) = args.value("larger-than") {
+        for dir in fs.dirs_larger_than(n) {
+            println!("{}\t{}", fs.nodes[dir].entry.size(), fs.path(dir));
+        }
+    }
+    if let Some(k) = args.value("largest-files") {
+        for file in fs.largest_files(k) {
+            println!("{}\t{}", fs.nodes[file].entry.size(), fs.path(file));
+        }
+    }
+    if let Some(needed) = args.value("free") {
+        match fs.smallest_dir_freeing(needed) {
+            Some(dir) => println!("{}\t{}", fs.nodes[dir].entry.size(), fs.path(dir)),
+            None => println!("no directory frees {}", needed),
+        }
+    }
+}
 
-    let mut delete_options: Vec<_> = fs
-        .dirs
-        .iter()
-        .map(|(name, _)| fs.dir_size(name))
-        .filter(|size| size > &to_free)
-        .collect();
+const SMALL_DIR_SIZE: usize = 100000;
+const REQUIRED_SPACE: usize = 30000000;
+const TOTAL_SPACE: usize = 70000000;
 
-    delete_options.sort();
+type NodeId = usize;
 
-    Solution::I32(*delete_options.first().unwrap() as i32)
-}
+const ROOT: NodeId = 0;
 
 #[derive(Debug)]
 struct FileInfo {
-    pub _name: String,
+    pub name: String,
     pub size: usize,
 }
 
 #[derive(Debug)]
 struct DirInfo {
     pub name: String,
+    // in the order they were listed
+    pub children: Vec<NodeId>,
+    pub by_name: HashMap<String, NodeId>,
+    // total size of everything below this directory, filled in once the tree is built
+    pub size: usize,
+}
+
+impl DirInfo {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            children: vec![],
+            by_name: HashMap::new(),
+            size: 0,
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -61,88 +126,410 @@ enum DirEntry {
     File(FileInfo),
 }
 
+impl DirEntry {
+    fn name(&self) -> &str {
+        match self {
+            DirEntry::Dir(DirInfo { name, .. }) => name,
+            DirEntry::File(FileInfo { name, .. }) => name,
+        }
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            DirEntry::Dir(DirInfo { size, .. }) | DirEntry::File(FileInfo { size, .. }) => *size,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Node {
+    pub parent: Option<NodeId>,
+    pub entry: DirEntry,
+}
+
+// arena of nodes, children always come after their parent
+#[derive(Debug)]
 struct FS {
-    pub dirs: HashMap<String, Vec<DirEntry>>,
+    pub nodes: Vec<Node>,
 }
 
 impl FS {
+    fn new() -> FS {
+        FS {
+            nodes: vec![Node {
+                parent: None,
+                entry: DirEntry::Dir(DirInfo::new("/".to_string())),
+            }],
+        }
+    }
+
+    // puts up with an inconsistent transcript, but not with one it cannot read
     fn build_from_input(input: &str) -> FS {
-        let lines: Vec<_> = input.split("\n").collect();
-        let mut cwd = "".to_string();
-        let mut dirs: HashMap<String, Vec<DirEntry>> = HashMap::new();
-        for line in lines {
-            if line.starts_with("$") {
-                let command: Vec<_> = line[2..line.len()].split(" ").collect();
-                match command[0] {
-                    "cd" => match command[1] {
-                        ".." => {
-                            let elements = cwd.split("/").collect::<Vec<_>>();
-                            cwd = elements[0..elements.len() - 1].join("/");
-                        }
-                        "/" => cwd = "/".to_string(),
-                        _ => {
-                            if cwd != "/" {
-                                cwd = format!("{}/{}", cwd, command[1]);
-                            } else {
-                                cwd = format!("/{}", command[1]);
-                            }
-                        }
-                    },
-                    "ls" => {}
-                    _ => panic!("Unknown command {}", line),
+        match FS::build(input) {
+            Ok((fs, _)) => fs,
+            Err(problems) => {
+                let report: Vec<_> = problems.iter().map(|p| p.to_string()).collect();
+                panic!("unreadable transcript:\n{}", report.join("\n"));
+            }
+        }
+    }
+
+    // like `build_from_input`, but any inconsistency in the transcript is an error
+    fn build_strict(input: &str) -> Result<FS, Vec<Problem>> {
+        match FS::build(input) {
+            Ok((fs, problems)) if problems.is_empty() => Ok(fs),
+            Ok((_, problems)) | Err(problems) => Err(problems),
+        }
+    }
+
+    // builds the tree as best it can and collects everything a real shell would not have
+    // printed, fails when some line is not a command or listing at all
+    fn build(input: &str) -> Result<(FS, Vec<Problem>), Vec<Problem>> {
+        let mut fs = FS::new();
+        let mut cwd = ROOT;
+        let mut listing = false;
+        let mut problems = vec![];
+        let mut unreadable = false;
+        for (i, line) in input.lines().enumerate() {
+            let mut problem = |message: String| problems.push(Problem::new(i + 1, message));
+            if line.trim().is_empty() {
+                continue;
+            }
+            if let Some(command) = line.strip_prefix("$ ") {
+                listing = false;
+                match command.split_whitespace().collect::<Vec<_>>().as_slice() {
+                    ["cd", path] => cwd = fs.cd(cwd, path, &mut problem),
+                    ["ls"] => listing = true,
+                    _ => {
+                        unreadable = true;
+                        problem(format!("unknown command {}", command));
+                    }
                 }
+                continue;
+            }
+            if !listing {
+                problem(format!("{} is not part of any ls output", line));
+            }
+            let Some((kind, name)) = line.split_once(" ") else {
+                unreadable = true;
+                problem(format!("malformed ls output {}", line));
+                continue;
+            };
+            let entry = if kind == "dir" {
+                DirEntry::Dir(DirInfo::new(name.to_string()))
+            } else if let Ok(size) = kind.parse() {
+                DirEntry::File(FileInfo {
+                    name: name.to_string(),
+                    size,
+                })
             } else {
-                let dir_info: Vec<_> = line.split(" ").collect();
-                let name = dir_info[1];
-                let dir_info = if dir_info[0] == "dir" {
-                    DirEntry::Dir(DirInfo {
-                        name: name.to_string(),
-                    })
-                } else {
-                    DirEntry::File(FileInfo {
-                        _name: name.to_string(),
-                        size: dir_info[0].parse().unwrap(),
-                    })
-                };
-                dirs.entry(cwd.clone()).or_default().push(dir_info);
+                unreadable = true;
+                problem(format!("invalid file size {}", kind));
+                continue;
+            };
+            if let Some(existing) = fs.child(cwd, name) {
+                match (&fs.nodes[existing].entry, &entry) {
+                    (DirEntry::Dir(_), DirEntry::Dir(_)) => {}
+                    (DirEntry::File(old), DirEntry::File(new)) if old.size == new.size => {}
+                    (DirEntry::File(old), DirEntry::File(new)) => problem(format!(
+                        "{} listed again with size {}, previously {}",
+                        name, new.size, old.size
+                    )),
+                    _ => problem(format!(
+                        "{} listed again as a different kind of entry",
+                        name
+                    )),
+                }
             }
+            fs.add(cwd, entry);
+        }
+        if unreadable {
+            return Err(problems);
+        }
+        fs.compute_sizes();
+        Ok((fs, problems))
+    }
+
+    fn children(&self, dir: NodeId) -> &[NodeId] {
+        match &self.nodes[dir].entry {
+            DirEntry::Dir(DirInfo { children, .. }) => children,
+            DirEntry::File(_) => &[],
+        }
+    }
+
+    fn child(&self, dir: NodeId, name: &str) -> Option<NodeId> {
+        match &self.nodes[dir].entry {
+            DirEntry::Dir(DirInfo { by_name, .. }) => by_name.get(name).copied(),
+            DirEntry::File(_) => None,
         }
-        FS { dirs }
     }
 
-    fn dir_size(&self, dir: &str) -> usize {
-        self.dirs
-            .get(dir)
-            .map(|files| {
-                files
+    // adds an entry to a directory unless one with that name was already listed
+    fn add(&mut self, dir: NodeId, entry: DirEntry) -> NodeId {
+        if let Some(existing) = self.child(dir, entry.name()) {
+            return existing;
+        }
+        let id = self.nodes.len();
+        let name = entry.name().to_string();
+        self.nodes.push(Node {
+            parent: Some(dir),
+            entry,
+        });
+        match &mut self.nodes[dir].entry {
+            DirEntry::Dir(DirInfo {
+                children, by_name, ..
+            }) => {
+                children.push(id);
+                by_name.insert(name, id);
+            }
+            DirEntry::File(_) => panic!("cannot add entries to a file"),
+        }
+        id
+    }
+
+    // follows a `cd` argument, creating directories that were never listed
+    fn cd(&mut self, from: NodeId, path: &str, problem: &mut impl FnMut(String)) -> NodeId {
+        let mut current = if path.starts_with('/') { ROOT } else { from };
+        for component in path.split('/').filter(|c| !c.is_empty()) {
+            current = match component {
+                "." => current,
+                ".." => self.nodes[current].parent.unwrap_or_else(|| {
+                    problem("cd .. from the root directory".to_string());
+                    ROOT
+                }),
+                name => match self.child(current, name) {
+                    Some(child) if matches!(self.nodes[child].entry, DirEntry::Dir(_)) => child,
+                    Some(_) => {
+                        problem(format!("cd into file {}", name));
+                        return current;
+                    }
+                    None => {
+                        problem(format!("cd into {} which was never listed", name));
+                        self.add(current, DirEntry::Dir(DirInfo::new(name.to_string())))
+                    }
+                },
+            };
+        }
+        current
+    }
+
+    fn lookup(&self, path: &str) -> Option<NodeId> {
+        let mut current = ROOT;
+        for component in path.split('/').filter(|c| !c.is_empty()) {
+            current = match component {
+                "." => current,
+                ".." => self.nodes[current].parent.unwrap_or(ROOT),
+                name => self.child(current, name)?,
+            };
+        }
+        Some(current)
+    }
+
+    fn compute_sizes(&mut self) {
+        for id in (0..self.nodes.len()).rev() {
+            if let DirEntry::Dir(_) = self.nodes[id].entry {
+                let size = self
+                    .children(id)
                     .iter()
-                    .map(|f| match f {
-                        DirEntry::Dir(DirInfo { name }) => {
-                            if dir == "/" {
-                                self.dir_size(format!("/{}", name).as_str())
-                            } else {
-                                self.dir_size(format!("{}/{}", dir, name).as_str())
-                            }
-                        }
-                        DirEntry::File(FileInfo { size, .. }) => *size,
-                    })
-                    .sum()
-            })
-            .unwrap_or(0)
+                    .map(|&c| self.nodes[c].entry.size())
+                    .sum();
+                if let DirEntry::Dir(dir) = &mut self.nodes[id].entry {
+                    dir.size = size;
+                }
+            }
+        }
+    }
+
+    fn dir_sizes(&self) -> impl Iterator<Item = usize> + '_ {
+        self.nodes.iter().filter_map(|node| match &node.entry {
+            DirEntry::Dir(DirInfo { size, .. }) => Some(*size),
+            DirEntry::File(_) => None,
+        })
+    }
+
+    // a `$ cd`/`$ ls` session that rebuilds this tree
+    fn transcript(&self) -> String {
+        let mut out = "$ cd /\n".to_string();
+        self.transcript_dir(ROOT, &mut out);
+        out
+    }
+
+    fn transcript_dir(&self, dir: NodeId, out: &mut String) {
+        out.push_str("$ ls\n");
+        for &child in self.children(dir) {
+            match &self.nodes[child].entry {
+                DirEntry::Dir(DirInfo { name, .. }) => out.push_str(&format!("dir {}\n", name)),
+                DirEntry::File(FileInfo { name, size }) => {
+                    out.push_str(&format!("{} {}\n", size, name))
+                }
+            }
+        }
+        for &child in self.children(dir) {
+            if let DirEntry::Dir(DirInfo { name, .. }) = &self.nodes[child].entry {
+                out.push_str(&format!("$ cd {}\n", name));
+                self.transcript_dir(child, out);
+                out.push_str("$ cd ..\n");
+            }
+        }
+    }
+
+    // a pseudo random tree for producing large inputs, the same seed gives the same tree
+    fn synthetic(num_dirs: usize, num_files: usize, seed: u64) -> FS {
+        let mut state = seed;
+        let mut next = |bound: usize| {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 33) as usize % bound
+        };
+        let mut fs = FS::new();
+        let mut dirs = vec![ROOT];
+        for i in 0..num_dirs {
+            let parent = dirs[next(dirs.len())];
+            dirs.push(fs.add(parent, DirEntry::Dir(DirInfo::new(format!("d{}", i)))));
+        }
+        for i in 0..num_files {
+            let parent = dirs[next(dirs.len())];
+            let extension = ["", ".txt", ".dat", ".log"][next(4)];
+            fs.add(
+                parent,
+                DirEntry::File(FileInfo {
+                    name: format!("f{}{}", i, extension),
+                    size: 1 + next(100000),
+                }),
+            );
+        }
+        fs.compute_sizes();
+        fs
+    }
+
+    fn path(&self, id: NodeId) -> String {
+        match self.nodes[id].parent {
+            None => "/".to_string(),
+            Some(ROOT) => format!("/{}", self.nodes[id].entry.name()),
+            Some(parent) => format!("{}/{}", self.path(parent), self.nodes[id].entry.name()),
+        }
+    }
+
+    fn tree(&self) -> String {
+        let mut out = format!("/ ({})\n", self.nodes[ROOT].entry.size());
+        self.tree_children(ROOT, "", &mut out);
+        out
+    }
+
+    fn tree_children(&self, dir: NodeId, prefix: &str, out: &mut String) {
+        let children = self.children(dir);
+        for (i, &child) in children.iter().enumerate() {
+            let last = i + 1 == children.len();
+            let entry = &self.nodes[child].entry;
+            out.push_str(&format!(
+                "{}{} {} ({})\n",
+                prefix,
+                if last { "└──" } else { "├──" },
+                entry.name(),
+                entry.size()
+            ));
+            let prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+            self.tree_children(child, &prefix, out);
+        }
+    }
+
+    // every file and directory like `du -a`, largest first
+    fn du(&self) -> String {
+        let mut lines: Vec<_> = (0..self.nodes.len())
+            .map(|id| (self.nodes[id].entry.size(), self.path(id)))
+            .collect();
+        lines.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| a.1.cmp(&b.1)));
+        lines
+            .iter()
+            .map(|(size, path)| format!("{}\t{}\n", size, path))
+            .collect()
+    }
+
+    fn json(&self, id: NodeId) -> String {
+        match &self.nodes[id].entry {
+            DirEntry::Dir(DirInfo {
+                name,
+                children,
+                size,
+                ..
+            }) => {
+                let children: Vec<_> = children.iter().map(|&c| self.json(c)).collect();
+                format!(
+                    "{{\"name\":{},\"type\":\"dir\",\"size\":{},\"children\":[{}]}}",
+                    json_string(name),
+                    size,
+                    children.join(",")
+                )
+            }
+            DirEntry::File(FileInfo { name, size }) => format!(
+                "{{\"name\":{},\"type\":\"file\",\"size\":{}}}",
+                json_string(name),
+                size
+            ),
+        }
+    }
+
+    fn dirs_larger_than(&self, n: usize) -> Vec<NodeId> {
+        (0..self.nodes.len())
+            .filter(|&id| matches!(self.nodes[id].entry, DirEntry::Dir(DirInfo { size, .. }) if size > n))
+            .collect()
+    }
+
+    fn largest_files(&self, k: usize) -> Vec<NodeId> {
+        let mut files: Vec<_> = (0..self.nodes.len())
+            .filter(|&id| matches!(self.nodes[id].entry, DirEntry::File(_)))
+            .collect();
+        files.sort_by_key(|&id| std::cmp::Reverse(self.nodes[id].entry.size()));
+        files.truncate(k);
+        files
+    }
+
+    // the directory to delete so the disk ends up with `needed` free space, an over-full disk
+    // first has to get back under its capacity
+    fn smallest_dir_freeing(&self, needed: usize) -> Option<NodeId> {
+        let to_free = self.nodes[ROOT]
+            .entry
+            .size()
+            .saturating_add(needed)
+            .saturating_sub(TOTAL_SPACE);
+        (0..self.nodes.len())
+            .filter(|&id| matches!(self.nodes[id].entry, DirEntry::Dir(DirInfo { size, .. }) if size >= to_free))
+            .min_by_key(|&id| self.nodes[id].entry.size())
+    }
+
+    // sum of the small directories and the size of the smallest directory freeing enough space
+    fn puzzle_answers(&self) -> (usize, Option<usize>) {
+        let small = self
+            .dir_sizes()
+            .filter(|&size| size <= SMALL_DIR_SIZE)
+            .sum();
+        let delete = self
+            .smallest_dir_freeing(REQUIRED_SPACE)
+            .map(|dir| self.nodes[dir].entry.size());
+        (small, delete)
     }
 }
 
-impl Debug for FS {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("FS").field("dirs", &self.dirs).finish()
+fn json_string(s: &str) -> String {
+    let mut out = "\"".to_string();
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
     }
+    out.push('"');
+    out
 }
 
 #[cfg(test)]
 mod test {
     use crate::{input::get_input, solution::Solution};
 
-    use super::{solve1, solve2, DAY};
+    use super::{json_string, solve1, solve2, DirEntry, Problem, DAY, FS, REQUIRED_SPACE};
 
     fn sample_input() -> String {
         get_input(DAY, true, None)
@@ -157,4 +544,161 @@ mod test {
     fn sample_2() {
         assert_eq!(Solution::I32(24933642), solve2(&sample_input()));
     }
+
+    #[test]
+    fn lookup() {
+        let fs = FS::build_from_input(&sample_input());
+        let e = fs.lookup("/a/e").unwrap();
+        assert_eq!(584, fs.nodes[e].entry.size());
+        assert_eq!(Some(e), fs.lookup("a/e/"));
+        assert_eq!(fs.lookup("/a"), fs.nodes[e].parent);
+        assert_eq!(
+            Some(8504156),
+            fs.lookup("/c.dat").map(|f| fs.nodes[f].entry.size())
+        );
+        assert_eq!(None, fs.lookup("/a/x"));
+    }
+
+    #[test]
+    fn absolute_cd_and_repeated_ls() {
+        let input =
+            sample_input() + "\n$ cd /a/e\n$ ls\n584 i\n$ cd /\n$ ls\ndir a\n14848514 b.txt\n";
+        assert_eq!(Solution::I32(95437), solve1(&input));
+        assert_eq!(Solution::I32(24933642), solve2(&input));
+        let fs = FS::build_from_input(&input);
+        assert_eq!(48381165, fs.nodes[0].entry.size());
+        assert!(matches!(
+            fs.nodes[fs.lookup("/a/e").unwrap()].entry,
+            DirEntry::Dir(_)
+        ));
+    }
+
+    #[test]
+    fn tree() {
+        let fs = FS::build_from_input(&sample_input());
+        assert_eq!(
+            "/ (48381165)
+├── a (94853)
+│   ├── e (584)
+│   │   └── i (584)
+│   ├── f (29116)
+│   ├── g (2557)
+│   └── h.lst (62596)
+├── b.txt (14848514)
+├── c.dat (8504156)
+└── d (24933642)
+    ├── j (4060174)
+    ├── d.log (8033020)
+    ├── d.ext (5626152)
+    └── k (7214296)
+",
+            fs.tree()
+        );
+    }
+
+    #[test]
+    fn du() {
+        let fs = FS::build_from_input(&sample_input());
+        let du = fs.du();
+        let lines: Vec<_> = du.lines().collect();
+        assert_eq!(14, lines.len());
+        assert_eq!("48381165\t/", lines[0]);
+        assert_eq!("24933642\t/d", lines[1]);
+        assert_eq!("584\t/a/e", lines[12]);
+        assert_eq!("584\t/a/e/i", lines[13]);
+    }
+
+    #[test]
+    fn json() {
+        let fs = FS::build_from_input(&sample_input());
+        let e = fs.lookup("/a/e").unwrap();
+        assert_eq!(
+            r#"{"name":"e","type":"dir","size":584,"children":[{"name":"i","type":"file","size":584}]}"#,
+            fs.json(e)
+        );
+        assert_eq!(r#""a\"b\\c\u0001""#, json_string("a\"b\\c\u{1}"));
+    }
+
+    #[test]
+    fn queries() {
+        let fs = FS::build_from_input(&sample_input());
+        let paths = |ids: Vec<usize>| ids.into_iter().map(|id| fs.path(id)).collect::<Vec<_>>();
+        assert_eq!(vec!["/", "/d"], paths(fs.dirs_larger_than(100000)));
+        assert_eq!(vec!["/b.txt", "/c.dat"], paths(fs.largest_files(2)));
+        assert_eq!(
+            Some("/d".to_string()),
+            fs.smallest_dir_freeing(REQUIRED_SPACE)
+                .map(|id| fs.path(id))
+        );
+        assert_eq!(None, fs.smallest_dir_freeing(usize::MAX));
+    }
+
+    #[test]
+    fn freeing_exactly_enough() {
+        // 20000000 free, a frees exactly the 10000000 still missing
+        let fs =
+            FS::build_from_input("$ cd /\n$ ls\ndir a\n40000000 b\n$ cd a\n$ ls\n10000000 c\n");
+        assert_eq!((0, Some(10000000)), fs.puzzle_answers());
+        // 10000000 over capacity, so a has to make room for that as well
+        let fs =
+            FS::build_from_input("$ cd /\n$ ls\ndir a\n40000000 b\n$ cd a\n$ ls\n40000000 c\n");
+        assert_eq!((0, Some(40000000)), fs.puzzle_answers());
+    }
+
+    #[test]
+    fn strict_sample() {
+        assert!(FS::build_strict(&sample_input()).is_ok());
+    }
+
+    #[test]
+    fn strict_problems() {
+        let input = "$ cd ..\n$ ls\ndir a\n10 b\n$ cd x\n$ cd /b\n12 c\n$ ls\n$ pwd\n$ cd /\n$ ls\n11 b\nq b\n";
+        assert_eq!(
+            vec![
+                Problem::new(1, "cd .. from the root directory"),
+                Problem::new(5, "cd into x which was never listed"),
+                Problem::new(6, "cd into file b"),
+                Problem::new(7, "12 c is not part of any ls output"),
+                Problem::new(9, "unknown command pwd"),
+                Problem::new(12, "b listed again with size 11, previously 10"),
+                Problem::new(13, "invalid file size q"),
+            ],
+            FS::build_strict(input).unwrap_err()
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "line 2: unknown command pwd")]
+    fn unknown_commands_are_fatal() {
+        FS::build_from_input("$ cd /\n$ pwd\n$ ls\n10 a\n");
+    }
+
+    #[test]
+    fn inconsistencies_are_tolerated() {
+        let fs = FS::build_from_input("$ cd /\n$ ls\n10 a\n$ cd b\n$ ls\n5 c\n");
+        assert_eq!(15, fs.nodes[0].entry.size());
+    }
+
+    #[test]
+    fn wide_directory() {
+        let files: Vec<_> = (0..20000).map(|i| format!("{} f{}", i, i)).collect();
+        let input = format!("$ cd /\n$ ls\n{}\n", files.join("\n"));
+        let fs = FS::build_strict(&input).unwrap();
+        assert_eq!(19999 * 20000 / 2, fs.nodes[0].entry.size());
+        assert_eq!(Some(12346), fs.lookup("/f12345"));
+    }
+
+    #[test]
+    fn transcript_round_trip() {
+        let fs = FS::build_from_input(&sample_input());
+        let transcript = fs.transcript();
+        let rebuilt = FS::build_strict(&transcript).unwrap();
+        assert_eq!(fs.json(0), rebuilt.json(0));
+        assert_eq!(transcript, rebuilt.transcript());
+
+        let synthetic = FS::synthetic(200, 500, 7);
+        let rebuilt = FS::build_strict(&synthetic.transcript()).unwrap();
+        assert_eq!(synthetic.json(0), rebuilt.json(0));
+        assert_eq!(synthetic.puzzle_answers(), rebuilt.puzzle_answers());
+    }
 }
@@ -1,6 +1,13 @@
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::{
+    collections::{HashMap, VecDeque},
+    fs,
+    io::{self, Write},
+    thread,
+    time::Duration,
+};
 
 use crate::{
+    cli::Args,
     input::get_input,
     solution::{Solution, SolutionPair},
 };
@@ -13,93 +20,336 @@ pub fn solve() -> SolutionPair {
 }
 
 fn solve1(input: &str) -> Solution {
-    let mut rope = Rope::init(input, 2);
+    let mut rope = Rope::<2>::init(input, 2, 1, &[1]);
     rope.advance_all();
-    Solution::I32(rope.tails_visited[&1].len() as i32)
+    Solution::I32(rope.visited(1) as i32)
 }
 
 fn solve2(input: &str) -> Solution {
-    let mut rope = Rope::init(input, 10);
+    let mut rope = Rope::<2>::init(input, 10, 1, &[9]);
     rope.advance_all();
-    Solution::I32(rope.tails_visited[&9].len() as i32)
+    Solution::I32(rope.visited(9) as i32)
+}
+
+pub fn tool(args: &Args) {
+    let input = args.input(DAY);
+    let knots = args.value("knots").unwrap_or(10);
+    let slack = args.value("slack").unwrap_or(1);
+    if knots == 0 {
+        println!("a rope needs at least one knot");
+        return;
+    }
+    if args.value::<usize>("dims").unwrap_or(2) == 3 {
+        let tracked: Vec<_> = (0..knots).collect();
+        let mut rope = Rope::<3>::init(&input, knots, slack, &tracked);
+        rope.advance_all();
+        for knot in 0..knots {
+            println!("knot {}: {} positions visited", knot, rope.visited(knot));
+        }
+        return;
+    }
+    let rope = Rope::<2>::init(&input, knots, slack, &[knots - 1]);
+    match args.value::<String>("dump") {
+        Some(path) => {
+            let mut file = fs::File::create(&path).unwrap();
+            dump(rope, &mut file).unwrap();
+        }
+        None => {
+            let delay = Duration::from_millis(args.value("delay-ms").unwrap_or(100));
+            play(rope, delay, &mut io::stdout()).unwrap();
+        }
+    }
+}
+
+type Point<const D: usize> = [i32; D];
+
+// U/D move along y, L/R along x and F/B along z, combined for diagonals ("UL", "DRF")
+fn parse_direction<const D: usize>(dir: &str) -> Point<D> {
+    let mut delta = [0; D];
+    for c in dir.chars() {
+        let (axis, amt) = match c {
+            'R' => (0, 1),
+            'L' => (0, -1),
+            'U' => (1, 1),
+            'D' => (1, -1),
+            'F' => (2, 1),
+            'B' => (2, -1),
+            _ => panic!("unknown direction {}", dir),
+        };
+        if axis >= D || delta[axis] != 0 {
+            panic!("invalid direction {} for {} dimensions", dir, D);
+        }
+        delta[axis] = amt;
+    }
+    delta
 }
 
-struct Rope {
-    tails_visited: HashMap<i32, HashSet<(i32, i32)>>,
-    positions: Vec<(i32, i32)>,
-    moves: VecDeque<(String, i32)>,
+const TILE: i32 = 8;
+
+// bitmap split into tiles of 8 cells along every axis, a tile only exists once a cell in it
+// is visited, so memory follows the visited cells rather than their bounding box
+#[derive(Clone)]
+struct VisitedGrid<const D: usize> {
+    tiles: HashMap<Point<D>, Vec<u64>>,
+    count: usize,
 }
 
-impl Rope {
-    fn init(input: &str, num_knots: i32) -> Self {
+impl<const D: usize> VisitedGrid<D> {
+    fn new() -> Self {
+        Self {
+            tiles: HashMap::new(),
+            count: 0,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.count
+    }
+
+    // the tile a point falls in and the point's bit within that tile
+    fn locate(point: Point<D>) -> (Point<D>, usize) {
+        let mut tile = [0; D];
+        let mut bit = 0;
+        for axis in (0..D).rev() {
+            tile[axis] = point[axis].div_euclid(TILE);
+            bit = bit * TILE as usize + point[axis].rem_euclid(TILE) as usize;
+        }
+        (tile, bit)
+    }
+
+    fn contains(&self, point: &Point<D>) -> bool {
+        let (tile, bit) = Self::locate(*point);
+        self.tiles
+            .get(&tile)
+            .is_some_and(|words| words[bit / 64] & (1 << (bit % 64)) != 0)
+    }
+
+    fn insert(&mut self, point: Point<D>) {
+        let (tile, bit) = Self::locate(point);
+        let words = self
+            .tiles
+            .entry(tile)
+            .or_insert_with(|| vec![0; (TILE as usize).pow(D as u32).div_ceil(64)]);
+        if words[bit / 64] & (1 << (bit % 64)) == 0 {
+            words[bit / 64] |= 1 << (bit % 64);
+            self.count += 1;
+        }
+    }
+}
+
+#[derive(Clone)]
+struct Rope<const D: usize> {
+    tails_visited: HashMap<usize, VisitedGrid<D>>,
+    positions: Vec<Point<D>>,
+    moves: VecDeque<(Point<D>, i32)>,
+    slack: i32,
+}
+
+impl<const D: usize> Rope<D> {
+    fn init(input: &str, num_knots: usize, slack: i32, tracked: &[usize]) -> Self {
         let moves = input
             .split("\n")
             .filter(|l| !l.trim().is_empty())
             .map(|line| {
                 let elements: Vec<_> = line.split(" ").collect();
-                (elements[0].to_string(), elements[1].parse().unwrap())
+                (parse_direction(elements[0]), elements[1].parse().unwrap())
             })
             .collect();
-        let mut tails_visited = HashMap::default();
-        let positions = (0..num_knots)
-            .map(|i| {
-                tails_visited.insert(i, HashSet::from([(0, 0)]));
-                (0, 0)
+        let tails_visited = tracked
+            .iter()
+            .map(|&i| {
+                let mut visited = VisitedGrid::new();
+                visited.insert([0; D]);
+                (i, visited)
             })
             .collect();
         Self {
             tails_visited,
-            positions,
+            positions: vec![[0; D]; num_knots],
             moves,
+            slack,
         }
     }
 
+    fn visited(&self, knot: usize) -> usize {
+        self.tails_visited[&knot].len()
+    }
+
     fn advance_all(&mut self) {
-        while !self.moves.is_empty() {
-            self.advance();
-        }
+        while self.step() {}
     }
 
-    fn advance(&mut self) {
-        let (dir, amt) = self.moves.pop_front().unwrap();
-        for _ in 0..amt {
-            match dir.as_str() {
-                "U" => self.positions[0] = (self.positions[0].0, self.positions[0].1 + 1),
-                "D" => self.positions[0] = (self.positions[0].0, self.positions[0].1 - 1),
-                "L" => self.positions[0] = (self.positions[0].0 - 1, self.positions[0].1),
-                "R" => self.positions[0] = (self.positions[0].0 + 1, self.positions[0].1),
-                _ => panic!("unknown direction {}", dir),
-            }
-            self.advance_tails()
+    fn steps(&mut self) -> Steps<'_, D> {
+        Steps { rope: self }
+    }
+
+    // moves the head a single cell, returns false once every move has been applied
+    fn step(&mut self) -> bool {
+        while let Some((_, 0)) = self.moves.front() {
+            self.moves.pop_front();
+        }
+        let Some((delta, amt)) = self.moves.front_mut() else {
+            return false;
+        };
+        *amt -= 1;
+        for (p, d) in self.positions[0].iter_mut().zip(delta.iter()) {
+            *p += d;
         }
+        if let Some(visited) = self.tails_visited.get_mut(&0) {
+            visited.insert(self.positions[0]);
+        }
+        self.advance_tails();
+        true
     }
 
     fn advance_tails(&mut self) {
         for i in 1..self.positions.len() {
-            if Self::distance(self.positions[i], self.positions[i - 1]) > 2f32.sqrt() {
-                let dx = self.positions[i - 1].0 - self.positions[i].0;
-                let dy = self.positions[i - 1].1 - self.positions[i].1;
-                self.positions[i] = (
-                    self.positions[i].0 + (dx.signum() * 1),
-                    self.positions[i].1 + (dy.signum() * 1),
-                );
-                self.tails_visited.entry(i as i32).and_modify(|e| {
-                    e.insert(self.positions[i]);
-                });
+            if Self::distance(self.positions[i], self.positions[i - 1]) > self.slack {
+                let leader = self.positions[i - 1];
+                for (p, l) in self.positions[i].iter_mut().zip(leader) {
+                    *p += (l - *p).signum();
+                }
+                if let Some(visited) = self.tails_visited.get_mut(&i) {
+                    visited.insert(self.positions[i]);
+                }
             }
         }
     }
 
-    fn distance(a: (i32, i32), b: (i32, i32)) -> f32 {
-        (((a.0 - b.0).pow(2) + (a.1 - b.1).pow(2)) as f32).sqrt()
+    // chebyshev distance, so diagonal neighbours count as touching
+    fn distance(a: Point<D>, b: Point<D>) -> i32 {
+        a.iter()
+            .zip(b)
+            .map(|(a, b)| (a - b).abs())
+            .max()
+            .unwrap_or(0)
+    }
+}
+
+struct Steps<'a, const D: usize> {
+    rope: &'a mut Rope<D>,
+}
+
+impl<const D: usize> Iterator for Steps<'_, D> {
+    type Item = Vec<Point<D>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.rope.step() {
+            Some(self.rope.positions.clone())
+        } else {
+            None
+        }
+    }
+}
+
+struct Bounds {
+    min: Point<2>,
+    max: Point<2>,
+}
+
+impl Bounds {
+    // every knot starts on the head and only ever steps towards its leader, so the rope
+    // stays within the box the head moves in and the moves alone are enough
+    fn of(rope: &Rope<2>) -> Self {
+        let mut head = rope.positions[0];
+        let mut bounds = Bounds {
+            min: head,
+            max: head,
+        };
+        for &([dx, dy], amt) in &rope.moves {
+            head = [head[0] + dx * amt, head[1] + dy * amt];
+            bounds.min = [bounds.min[0].min(head[0]), bounds.min[1].min(head[1])];
+            bounds.max = [bounds.max[0].max(head[0]), bounds.max[1].max(head[1])];
+        }
+        bounds
+    }
+}
+
+fn knot_char(index: usize, num_knots: usize) -> char {
+    match index {
+        0 => 'H',
+        1 if num_knots == 2 => 'T',
+        i => char::from_digit((i % 10) as u32, 10).unwrap(),
+    }
+}
+
+fn render_frame(positions: &[Point<2>], visited: &VisitedGrid<2>, bounds: &Bounds) -> String {
+    (bounds.min[1]..=bounds.max[1])
+        .rev()
+        .map(|y| {
+            (bounds.min[0]..=bounds.max[0])
+                .map(|x| {
+                    if let Some(i) = positions.iter().position(|p| *p == [x, y]) {
+                        knot_char(i, positions.len())
+                    } else if [x, y] == [0, 0] {
+                        's'
+                    } else if visited.contains(&[x, y]) {
+                        '#'
+                    } else {
+                        '.'
+                    }
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// the rope before the first step and after every step, rendered one frame at a time
+struct Frames {
+    rope: Rope<2>,
+    bounds: Bounds,
+    started: bool,
+}
+
+impl Iterator for Frames {
+    type Item = String;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let positions = if self.started {
+            self.rope.steps().next()?
+        } else {
+            self.rope.positions.clone()
+        };
+        self.started = true;
+        let tail = positions.len() - 1;
+        Some(render_frame(
+            &positions,
+            &self.rope.tails_visited[&tail],
+            &self.bounds,
+        ))
+    }
+}
+
+fn frames(rope: Rope<2>) -> Frames {
+    Frames {
+        bounds: Bounds::of(&rope),
+        rope,
+        started: false,
     }
 }
 
+fn play(rope: Rope<2>, delay: Duration, out: &mut impl Write) -> io::Result<()> {
+    for frame in frames(rope) {
+        writeln!(out, "\x1b[2J\x1b[H{}", frame)?;
+        out.flush()?;
+        thread::sleep(delay);
+    }
+    Ok(())
+}
+
+fn dump(rope: Rope<2>, out: &mut impl Write) -> io::Result<()> {
+    for frame in frames(rope) {
+        write!(out, "{}\n\n", frame)?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod test {
     use crate::{input::get_input, solution::Solution};
 
-    use super::{solve1, solve2, DAY};
+    use super::{dump, solve1, solve2, Bounds, Rope, VisitedGrid, DAY};
 
     fn sample_input() -> String {
         get_input(DAY, true, None)
@@ -119,4 +369,97 @@ mod test {
     fn sample_2_v2() {
         assert_eq!(Solution::I32(36), solve2(&get_input(DAY, true, Some("2"))));
     }
+
+    #[test]
+    fn steps_v2() {
+        let mut rope = Rope::<2>::init(&get_input(DAY, true, Some("2")), 10, 1, &[9]);
+        let steps: Vec<_> = rope.steps().collect();
+        assert_eq!(5 + 8 + 8 + 3 + 17 + 10 + 25 + 20, steps.len());
+        assert_eq!(Some(&[-11, 15]), steps.last().unwrap().first());
+        assert_eq!(36, rope.visited(9));
+    }
+
+    #[test]
+    fn head_bounds_cover_every_knot() {
+        let mut rope = Rope::<2>::init(&get_input(DAY, true, Some("2")), 10, 1, &[9]);
+        let bounds = Bounds::of(&rope);
+        let (mut min, mut max) = ([0, 0], [0, 0]);
+        for [x, y] in rope.steps().flatten() {
+            min = [min[0].min(x), min[1].min(y)];
+            max = [max[0].max(x), max[1].max(y)];
+        }
+        assert_eq!((min, max), (bounds.min, bounds.max));
+    }
+
+    #[test]
+    fn dump_sample() {
+        let mut out = vec![];
+        dump(Rope::init(&sample_input(), 2, 1, &[1]), &mut out).unwrap();
+        let frames = String::from_utf8(out).unwrap();
+        let frames: Vec<_> = frames.trim_end().split("\n\n").collect();
+        assert_eq!(25, frames.len());
+        assert_eq!(
+            "..##..\n...##.\n.TH##.\n....#.\ns###..",
+            *frames.last().unwrap()
+        );
+    }
+
+    #[test]
+    fn diagonal_moves() {
+        let mut rope = Rope::<2>::init("UR 3\nDL 1\n", 2, 1, &[1]);
+        rope.advance_all();
+        assert_eq!([[2, 2], [2, 2]], rope.positions[..]);
+        assert_eq!(3, rope.visited(1));
+    }
+
+    #[test]
+    fn slack() {
+        let mut rope = Rope::<2>::init(&sample_input(), 2, 2, &[0, 1]);
+        rope.advance_all();
+        // the tail only moves at distance 3: (1, 0), (2, 0), (3, 1), (4, 2), (3, 3) and (2, 2)
+        assert_eq!(7, rope.visited(1));
+        assert_eq!(rope.visited(0), {
+            let mut head = Rope::<2>::init(&sample_input(), 1, 1, &[0]);
+            head.advance_all();
+            head.visited(0)
+        });
+    }
+
+    #[test]
+    fn three_dimensions() {
+        let mut rope = Rope::<3>::init("F 3\nUR 2\nB 1\n", 3, 1, &[2]);
+        rope.advance_all();
+        assert_eq!([[2, 2, 2], [1, 1, 3], [1, 1, 2]], rope.positions[..]);
+        assert_eq!(3, rope.visited(2));
+    }
+
+    #[test]
+    fn untracked_knots() {
+        let mut rope = Rope::<2>::init(&sample_input(), 10, 1, &[1]);
+        rope.advance_all();
+        assert_eq!(1, rope.tails_visited.len());
+        assert_eq!(13, rope.visited(1));
+    }
+
+    #[test]
+    fn long_sparse_path() {
+        let mut rope = Rope::<3>::init("R 30000\nU 30000\nF 3000\n", 2, 1, &[1]);
+        rope.advance_all();
+        assert_eq!(30000 + 29999 + 2999, rope.visited(1));
+        // one tile per 8 cells of the path, nowhere near the 30000 by 30000 box around it
+        assert!(rope.tails_visited[&1].tiles.len() < 10000);
+    }
+
+    #[test]
+    fn visited_grid_grows() {
+        let mut grid = VisitedGrid::<2>::new();
+        let points = [[0, 0], [-70, 3], [5, -200], [-70, 3], [64, 64], [1, 0]];
+        for p in points {
+            grid.insert(p);
+        }
+        assert_eq!(5, grid.len());
+        assert!(points.iter().all(|p| grid.contains(p)));
+        assert!(!grid.contains(&[0, 1]));
+        assert!(!grid.contains(&[1000, 0]));
+    }
 }
@@ -0,0 +1,275 @@
+use std::{
+    fmt::{self, Display},
+    str::FromStr,
+};
+
+// the integers from start to end, both included
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Interval {
+    pub start: i64,
+    pub end: i64,
+}
+
+impl Interval {
+    pub fn new(start: i64, end: i64) -> Self {
+        assert!(start <= end, "empty interval {}-{}", start, end);
+        Self { start, end }
+    }
+
+    // wider than u64, the whole i64 range has 2^64 integers
+    pub fn len(&self) -> u128 {
+        self.end.abs_diff(self.start) as u128 + 1
+    }
+
+    pub fn contains(&self, other: &Interval) -> bool {
+        self.start <= other.start && other.end <= self.end
+    }
+
+    pub fn overlaps(&self, other: &Interval) -> bool {
+        self.start <= other.end && other.start <= self.end
+    }
+
+    pub fn intersection(&self, other: &Interval) -> Option<Interval> {
+        self.overlaps(other)
+            .then(|| Interval::new(self.start.max(other.start), self.end.min(other.end)))
+    }
+}
+
+impl FromStr for Interval {
+    type Err = String;
+
+    // `start-end`, negative numbers are allowed on both sides
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let split = s
+            .char_indices()
+            .skip(1)
+            .find(|&(_, c)| c == '-')
+            .map(|(i, _)| i)
+            .ok_or_else(|| format!("expected start-end, got {}", s))?;
+        let parse = |n: &str| {
+            n.trim()
+                .parse::<i64>()
+                .map_err(|_| format!("invalid section {} in {}", n, s))
+        };
+        let (start, end) = (parse(&s[..split])?, parse(&s[split + 1..])?);
+        if start > end {
+            return Err(format!("{} ends before it starts", s));
+        }
+        Ok(Interval::new(start, end))
+    }
+}
+
+impl Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
+// sorted intervals that neither overlap nor touch
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct IntervalSet {
+    intervals: Vec<Interval>,
+}
+
+impl IntervalSet {
+    pub fn from_intervals(intervals: impl IntoIterator<Item = Interval>) -> Self {
+        let mut sorted: Vec<_> = intervals.into_iter().collect();
+        sorted.sort();
+        let mut merged: Vec<Interval> = Vec::with_capacity(sorted.len());
+        for interval in sorted {
+            match merged.last_mut() {
+                Some(last) if interval.start <= last.end.saturating_add(1) => {
+                    last.end = last.end.max(interval.end)
+                }
+                _ => merged.push(interval),
+            }
+        }
+        Self { intervals: merged }
+    }
+
+    pub fn intervals(&self) -> &[Interval] {
+        &self.intervals
+    }
+
+    // the number of integers in the set
+    pub fn len(&self) -> u128 {
+        self.intervals.iter().map(Interval::len).sum()
+    }
+
+    pub fn union(&self, other: &IntervalSet) -> IntervalSet {
+        IntervalSet::from_intervals(self.intervals.iter().chain(&other.intervals).copied())
+    }
+
+    pub fn intersection(&self, other: &IntervalSet) -> IntervalSet {
+        let (mut i, mut j) = (0, 0);
+        let mut out = vec![];
+        while i < self.intervals.len() && j < other.intervals.len() {
+            let (a, b) = (self.intervals[i], other.intervals[j]);
+            out.extend(a.intersection(&b));
+            if a.end < b.end {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        IntervalSet { intervals: out }
+    }
+
+    pub fn difference(&self, other: &IntervalSet) -> IntervalSet {
+        let mut out = vec![];
+        let mut j = 0;
+        for &interval in &self.intervals {
+            let mut start = interval.start;
+            // skip what ends before this interval, it cannot cut anything later either
+            while j < other.intervals.len() && other.intervals[j].end < start {
+                j += 1;
+            }
+            let mut k = j;
+            while k < other.intervals.len() && other.intervals[k].start <= interval.end {
+                let cut = other.intervals[k];
+                if cut.start > start {
+                    out.push(Interval::new(start, cut.start - 1));
+                }
+                if cut.end >= interval.end {
+                    start = interval.end.saturating_add(1);
+                    break;
+                }
+                start = cut.end + 1;
+                k += 1;
+            }
+            if start <= interval.end {
+                out.push(Interval::new(start, interval.end));
+            }
+        }
+        IntervalSet { intervals: out }
+    }
+}
+
+impl From<Interval> for IntervalSet {
+    fn from(interval: Interval) -> Self {
+        Self {
+            intervals: vec![interval],
+        }
+    }
+}
+
+// splits the span of the intervals into runs covered by the same number of them, in order
+pub fn coverage(intervals: &[Interval]) -> Vec<(Interval, usize)> {
+    // an interval ending at i64::MAX has nowhere to end, it stays open past the last event
+    let mut events: Vec<(i64, i64)> = intervals
+        .iter()
+        .flat_map(|i| {
+            [
+                Some((i.start, 1)),
+                i.end.checked_add(1).map(|after| (after, -1)),
+            ]
+        })
+        .flatten()
+        .collect();
+    events.sort();
+    let mut runs = vec![];
+    let mut depth = 0;
+    for (k, &(at, change)) in events.iter().enumerate() {
+        depth += change;
+        match events.get(k + 1) {
+            Some(&(next, _)) if next > at => {
+                runs.push((Interval::new(at, next - 1), depth as usize))
+            }
+            None if depth > 0 => runs.push((Interval::new(at, i64::MAX), depth as usize)),
+            _ => {}
+        }
+    }
+    runs
+}
+
+#[cfg(test)]
+mod test {
+    use super::{coverage, Interval, IntervalSet};
+
+    fn set(intervals: &[(i64, i64)]) -> IntervalSet {
+        IntervalSet::from_intervals(intervals.iter().map(|&(s, e)| Interval::new(s, e)))
+    }
+
+    #[test]
+    fn predicates() {
+        let a = Interval::new(2, 8);
+        assert!(a.contains(&Interval::new(3, 7)));
+        assert!(a.contains(&a));
+        assert!(!a.contains(&Interval::new(1, 7)));
+        assert!(a.overlaps(&Interval::new(8, 9)));
+        assert!(!a.overlaps(&Interval::new(9, 9)));
+        assert_eq!(
+            Some(Interval::new(5, 8)),
+            a.intersection(&Interval::new(5, 1_000_000_000))
+        );
+        assert_eq!(1_000_000_000, Interval::new(1, 1_000_000_000).len());
+    }
+
+    #[test]
+    fn parse() {
+        assert_eq!(Ok(Interval::new(-5, -2)), "-5--2".parse());
+        assert_eq!(Ok(Interval::new(3, 3)), "3-3".parse());
+        assert!("7-3".parse::<Interval>().is_err());
+        assert!("7".parse::<Interval>().is_err());
+        assert!("a-3".parse::<Interval>().is_err());
+    }
+
+    #[test]
+    fn set_operations() {
+        let a = set(&[(1, 3), (10, 20), (4, 5), (30, 40)]);
+        assert_eq!(
+            set(&[(1, 5), (10, 20), (30, 40)]).intervals(),
+            a.intervals()
+        );
+        assert_eq!(27, a.len());
+        let b = set(&[(2, 2), (15, 35)]);
+        assert_eq!(set(&[(1, 5), (10, 40)]), a.union(&b));
+        assert_eq!(set(&[(2, 2), (15, 20), (30, 35)]), a.intersection(&b));
+        assert_eq!(set(&[(1, 1), (3, 5), (10, 14), (36, 40)]), a.difference(&b));
+        assert_eq!(set(&[(21, 29)]), b.difference(&a));
+        assert_eq!(IntervalSet::default(), a.difference(&a));
+    }
+
+    #[test]
+    fn extremes() {
+        let everything = Interval::new(i64::MIN, i64::MAX);
+        assert_eq!(1 << 64, everything.len());
+        assert_eq!(
+            set(&[(i64::MIN, -1), (1, i64::MAX)]),
+            IntervalSet::from(everything).difference(&set(&[(0, 0)]))
+        );
+        assert_eq!((1 << 64) - 1, set(&[(i64::MIN, -1), (1, i64::MAX)]).len());
+        assert_eq!(
+            vec![
+                (Interval::new(i64::MIN, i64::MIN + 1), 1),
+                (Interval::new(i64::MIN + 2, i64::MAX - 2), 0),
+                (Interval::new(i64::MAX - 1, i64::MAX - 1), 1),
+                (Interval::new(i64::MAX, i64::MAX), 2),
+            ],
+            coverage(&[
+                Interval::new(i64::MAX - 1, i64::MAX),
+                Interval::new(i64::MIN, i64::MIN + 1),
+                Interval::new(i64::MAX, i64::MAX),
+            ])
+        );
+    }
+
+    #[test]
+    fn coverage_runs() {
+        let runs = coverage(&[
+            Interval::new(1, 4),
+            Interval::new(3, 6),
+            Interval::new(9, 9),
+        ]);
+        assert_eq!(
+            vec![
+                (Interval::new(1, 2), 1),
+                (Interval::new(3, 4), 2),
+                (Interval::new(5, 6), 1),
+                (Interval::new(7, 8), 0),
+                (Interval::new(9, 9), 1),
+            ],
+            runs
+        );
+    }
+}
@@ -5,8 +5,14 @@
 
 use std::{env, time::Instant};
 
+mod bigint;
+mod cli;
+mod cpu;
+mod cycle;
 mod days;
 mod input;
+mod interval;
+mod problem;
 mod solution;
 
 use solution::SolutionPair;
@@ -22,6 +28,12 @@ fn main() {
 
     let day_arg: u32 = args[1].parse().unwrap();
 
+    if args.len() > 2 {
+        let tool = get_tool(day_arg);
+        tool(&cli::Args::parse(&args[2..]));
+        return;
+    }
+
     let runner = get_day(day_arg);
 
     let time = Instant::now();
@@ -51,3 +63,18 @@ fn get_day(day: u32) -> fn() -> SolutionPair {
 	_ => unimplemented!("day {} is unimplemented", day),
     }
 }
+
+fn get_tool(day: u32) -> fn(&cli::Args) {
+    match day {
+        2 => day2::tool,
+        3 => day3::tool,
+        4 => day4::tool,
+        5 => day5::tool,
+        6 => day6::tool,
+        7 => day7::tool,
+        9 => day9::tool,
+        10 => day10::tool,
+        11 => day11::tool,
+        _ => panic!("day {} has no tools", day),
+    }
+}